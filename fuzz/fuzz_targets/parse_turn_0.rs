@@ -0,0 +1,6 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ants_ai_challenge_api::parse_turn_0(&String::from_utf8_lossy(data));
+});
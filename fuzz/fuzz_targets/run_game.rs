@@ -0,0 +1,23 @@
+#![no_main]
+use ants_ai_challenge_api::*;
+use libfuzzer_sys::fuzz_target;
+
+/// Orders every own ant north, so that orders get serialized too.
+struct North;
+
+impl Agent for North {
+    fn prepare(&mut self, _params: GameParameters) {}
+
+    fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+        world
+            .live_ants_for_player(0)
+            .iter()
+            .map(|a| a.order(Direction::North))
+            .collect()
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let _ = try_run_game_with_io(&mut North, text.lines().map(String::from), &mut |_| ());
+});
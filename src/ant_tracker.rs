@@ -0,0 +1,256 @@
+//! Stable identities for ants across turns, which the protocol does not
+//! give. Our ants are followed by the orders given to them, enemy ants on a
+//! best-effort basis by proximity.
+//!
+//! # Example
+//! ```
+//! use ants_ai_challenge_api::ant_tracker::AntTracker;
+//! use ants_ai_challenge_api::*;
+//!
+//! let params = GameParameters {
+//!     rows: 10,
+//!     cols: 10,
+//!     ..GameParameters::official()
+//! };
+//! let mut tracker = AntTracker::new(params);
+//!
+//! tracker.update(&WorldState::default().live_ant(pos(1, 1), 0), &[]);
+//! let id = tracker.id_at(&pos(1, 1)).unwrap();
+//!
+//! tracker.update(&WorldState::default().live_ant(pos(1, 2), 0), &[pos(1, 1).east()]);
+//! assert_eq!(Some(id), tracker.id_at(&pos(1, 2)));
+//! ```
+
+use super::map_memory::MapMemory;
+use super::world_diff::{OwnAnt, WorldDiff};
+use super::{GameParameters, Order, Position, WorldState};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Identity of an ant, unique within a tracker.
+#[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Clone, Copy)]
+pub struct AntId(pub u32);
+
+/// Follows ants from turn to turn. Call `update` once per turn with the
+/// new world and the orders given in the previous turn.
+#[derive(Debug, Clone)]
+pub struct AntTracker {
+    params: GameParameters,
+    world: WorldState,
+    /// Water of the previous turns, which is only sent once.
+    memory: MapMemory,
+    next_id: u32,
+    own: BTreeMap<Position, AntId>,
+    enemies: BTreeMap<Position, (AntId, u8)>,
+    spawned: Vec<AntId>,
+    lost: Vec<AntId>,
+}
+
+impl AntTracker {
+    pub fn new(params: GameParameters) -> Self {
+        AntTracker {
+            memory: MapMemory::new(&params),
+            params,
+            world: WorldState::default(),
+            next_id: 0,
+            own: BTreeMap::new(),
+            enemies: BTreeMap::new(),
+            spawned: vec![],
+            lost: vec![],
+        }
+    }
+
+    /// Match the ants of the new world to the ones of the previous world.
+    ///
+    /// Our ants keep their identity when found where their order took
+    /// them, or where they were or next to it if the order had no effect.
+    /// Ants that died, e.g. in collisions, are lost, and ants without
+    /// predecessor, normally spawned at hills, get new identities. Enemy
+    /// ants keep the identity of an ant of the same owner that was on the
+    /// tile or next to it, preferring ants that stayed.
+    pub fn update(&mut self, world: &WorldState, orders: &[Order]) {
+        let diff = WorldDiff::between(&self.world, world, orders, &self.memory, &self.params);
+        self.spawned.clear();
+        self.lost.clear();
+
+        let mut own = BTreeMap::new();
+        let mut unmatched: BTreeSet<Position> = diff.own_spawned.iter().cloned().collect();
+        for fate in diff.own_ants {
+            let (from, to) = match fate {
+                OwnAnt::Moved { from, to } => (from, Some(to)),
+                OwnAnt::Died { from, .. } => (from, None),
+                OwnAnt::Missing { from } => {
+                    let nearby = self.nearby(&from, |p| unmatched.contains(p));
+                    if let Some(p) = &nearby {
+                        unmatched.remove(p);
+                    }
+                    (from, nearby)
+                }
+            };
+            let id = self.own[&from];
+            match to {
+                Some(to) if !own.contains_key(&to) => {
+                    own.insert(to, id);
+                }
+                _ => self.lost.push(id),
+            }
+        }
+        for p in diff.own_spawned {
+            if unmatched.contains(&p) {
+                let id = self.new_id();
+                self.spawned.push(id);
+                own.insert(p, id);
+            }
+        }
+        self.own = own;
+
+        let previous = std::mem::take(&mut self.enemies);
+        let mut claimed = BTreeSet::new();
+        for (player, ants) in world.live_ants.iter().enumerate().skip(1) {
+            let owner = player as u8;
+            for p in ants {
+                let same_owner = |q: &Position| {
+                    !claimed.contains(q) && previous.get(q).map(|(_, o)| *o) == Some(owner)
+                };
+                let id = match self.nearby(p, same_owner) {
+                    Some(q) => {
+                        let id = previous[&q].0;
+                        claimed.insert(q);
+                        id
+                    }
+                    None => self.new_id(),
+                };
+                self.enemies.insert(p.clone(), (id, owner));
+            }
+        }
+
+        self.memory.update(world, self.memory.turn() + 1);
+        self.world = world.clone();
+    }
+
+    /// Our ant at a position.
+    pub fn id_at(&self, p: &Position) -> Option<AntId> {
+        self.own.get(p).copied()
+    }
+
+    /// Position of our ant.
+    pub fn position_of(&self, id: AntId) -> Option<&Position> {
+        self.own.iter().find(|(_, i)| **i == id).map(|(p, _)| p)
+    }
+
+    /// Our ants with their positions, ordered by position.
+    pub fn ants(&self) -> impl Iterator<Item = (AntId, &Position)> {
+        self.own.iter().map(|(p, id)| (*id, p))
+    }
+
+    /// Enemy ant at a position, with its owner.
+    pub fn enemy_at(&self, p: &Position) -> Option<(AntId, u8)> {
+        self.enemies.get(p).copied()
+    }
+
+    /// Visible enemy ants with their owners and positions, ordered by
+    /// position.
+    pub fn enemies(&self) -> impl Iterator<Item = (AntId, u8, &Position)> {
+        self.enemies.iter().map(|(p, (id, owner))| (*id, *owner, p))
+    }
+
+    /// Our ants that were new in the last update.
+    pub fn spawned(&self) -> &[AntId] {
+        &self.spawned
+    }
+
+    /// Our ants that were dead or could not be found in the last update.
+    pub fn lost(&self) -> &[AntId] {
+        &self.lost
+    }
+
+    fn new_id(&mut self) -> AntId {
+        let id = AntId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Given position or its first neighbour satisfying the predicate.
+    fn nearby<F: Fn(&Position) -> bool>(&self, p: &Position, predicate: F) -> Option<Position> {
+        let size = Position {
+            row: self.params.rows as u16,
+            col: self.params.cols as u16,
+        };
+        std::iter::once(p.clone())
+            .chain(p.neighbours(&size))
+            .find(|q| predicate(q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::super::scenario::world;
+    use super::*;
+
+    fn tracker() -> AntTracker {
+        AntTracker::new(GameParameters {
+            rows: 3,
+            cols: 6,
+            ..GameParameters::official()
+        })
+    }
+
+    #[test]
+    fn follows_orders_and_spawns() {
+        let mut tracker = tracker();
+        tracker.update(&world("a.a...\n......\n.0...."), &[]);
+        let (first, second) = (AntId(0), AntId(1));
+        assert_eq!(vec![first, second], tracker.spawned());
+        assert_eq!(Some(first), tracker.id_at(&pos(0, 0)));
+
+        // A new ant appears on the hill
+        tracker.update(
+            &world("......\na.a...\n.A...."),
+            &[pos(0, 0).south(), pos(0, 2).south()],
+        );
+        assert!(tracker.lost().is_empty());
+        assert_eq!(vec![AntId(2)], tracker.spawned());
+
+        tracker.update(
+            &world("..a...\n.a....\n.A...."),
+            &[pos(1, 0).east(), pos(1, 2).north()],
+        );
+        assert_eq!(Some(&pos(1, 1)), tracker.position_of(AntId(0)));
+        assert_eq!(Some(&pos(0, 2)), tracker.position_of(AntId(1)));
+        assert_eq!(Some(AntId(2)), tracker.id_at(&pos(2, 1)));
+        assert!(tracker.spawned().is_empty());
+    }
+
+    #[test]
+    fn collisions_and_ignored_orders() {
+        let mut tracker = tracker();
+        tracker.update(&world("a.a.a.\n......\n......"), &[]);
+
+        // First two collide, the order of the third has no effect
+        let next = world("....a.\n......\n......").dead_ant(pos(0, 1), 0);
+        tracker.update(
+            &next,
+            &[pos(0, 0).east(), pos(0, 2).west(), pos(0, 4).east()],
+        );
+
+        assert_eq!(vec![AntId(0), AntId(1)], tracker.lost());
+        assert_eq!(Some(AntId(2)), tracker.id_at(&pos(0, 4)));
+        assert_eq!(1, tracker.ants().count());
+        assert!(tracker.spawned().is_empty());
+    }
+
+    #[test]
+    fn enemies_by_proximity() {
+        let mut tracker = tracker();
+        tracker.update(&world("b...c.\n......\n......"), &[]);
+        let b = tracker.enemy_at(&pos(0, 0)).unwrap();
+        let c = tracker.enemy_at(&pos(0, 4)).unwrap();
+        assert_eq!(1, b.1);
+        assert_eq!(2, c.1);
+
+        tracker.update(&world(".b....\n......\n...c.."), &[]);
+        assert_eq!(Some(b), tracker.enemy_at(&pos(0, 1)));
+        assert_ne!(Some(c.0), tracker.enemy_at(&pos(2, 3)).map(|e| e.0));
+        assert_eq!(2, tracker.enemies().count());
+    }
+}
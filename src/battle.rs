@@ -0,0 +1,473 @@
+//! Local battle search. Ants close enough to fight next turn are grouped
+//! into clusters, and for each cluster the joint moves of our ants are
+//! tried against the joint moves of the enemies, resolved with the official
+//! focus combat rule. Our best joint move is the one with the best outcome
+//! against the enemies' best reply.
+
+use super::map_memory::MapMemory;
+use super::{pos, Direction, GameParameters, Orders, Position, WorldState};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct BattleParameters {
+    /// Most ants per side that move in the search. Further ants of a
+    /// cluster stay where they are, those furthest from the enemy first.
+    pub max_ants: usize,
+    /// Value of losing one of our ants, compared to 1 for killing an enemy.
+    pub loss_weight: f64,
+    /// Time for the search of all clusters.
+    pub time_limit: Duration,
+}
+
+impl Default for BattleParameters {
+    fn default() -> Self {
+        BattleParameters {
+            max_ants: 4,
+            loss_weight: 1.1,
+            time_limit: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Ants that may fight each other after their next move.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Cluster {
+    pub own: Vec<Position>,
+    /// Enemy ants with their owners.
+    pub enemies: Vec<(Position, u8)>,
+}
+
+/// Clusters with both our and enemy ants. Ants belong to the same cluster
+/// when they are linked by ants that could be within attack range of each
+/// other after both move.
+pub fn clusters(world: &WorldState, params: &GameParameters) -> Vec<Cluster> {
+    let size = size(params);
+    let ants: Vec<(Position, u8)> = world
+        .live_ants
+        .iter()
+        .enumerate()
+        .flat_map(|(owner, ants)| ants.iter().map(move |p| (p.clone(), owner as u8)))
+        .collect();
+    let reach = (params.attackradius2.max(0) as f64).sqrt() + 2.0;
+
+    // Union find over the ants
+    let mut parent: Vec<usize> = (0..ants.len()).collect();
+    fn root(parent: &mut [usize], i: usize) -> usize {
+        let mut r = i;
+        while parent[r] != r {
+            r = parent[r];
+        }
+        parent[i] = r;
+        r
+    }
+    for i in 0..ants.len() {
+        for j in i + 1..ants.len() {
+            if (ants[i].0.distance2(&ants[j].0, &size) as f64) <= reach * reach {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = vec![Cluster::default(); ants.len()];
+    for (i, (p, owner)) in ants.iter().enumerate() {
+        let cluster = &mut clusters[root(&mut parent, i)];
+        if *owner == 0 {
+            cluster.own.push(p.clone());
+        } else {
+            cluster.enemies.push((p.clone(), *owner));
+        }
+    }
+    clusters
+        .into_iter()
+        .filter(|c| !c.own.is_empty() && !c.enemies.is_empty())
+        .collect()
+}
+
+/// Our best joint move for every cluster, as an order per ant of the
+/// clusters, with holds for ants that stay. The time limit is shared by
+/// the clusters.
+pub fn battle_orders(
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+    battle: &BattleParameters,
+) -> Orders {
+    let start = Instant::now();
+    let clusters = clusters(world, params);
+    let mut orders = vec![];
+    for (i, cluster) in clusters.iter().enumerate() {
+        let left = battle.time_limit.saturating_sub(start.elapsed());
+        let share = BattleParameters {
+            time_limit: left / (clusters.len() - i) as u32,
+            ..battle.clone()
+        };
+        orders.extend(best_orders(cluster, world, memory, params, &share));
+    }
+    orders
+}
+
+/// Our best joint move for the cluster, assuming the enemies reply as badly
+/// for us as they can. Moves are tried in a fixed order, staying first, so
+/// the result is deterministic when the search completes. When time runs
+/// out, the best move found so far is used; at least one is always tried.
+///
+/// Ants do not move onto water, of the world or seen in earlier turns as
+/// remembered, and our ants do not move onto the same tile.
+pub fn best_orders(
+    cluster: &Cluster,
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+    battle: &BattleParameters,
+) -> Orders {
+    let start = Instant::now();
+    let size = size(params);
+    let sent: BTreeSet<&Position> = world.waters.iter().collect();
+    let water = |p: &Position| sent.contains(p) || memory.is_water(p);
+
+    let enemy_positions: Vec<Position> = cluster.enemies.iter().map(|(p, _)| p.clone()).collect();
+    let (own_active, own_frozen) = split_active(&cluster.own, &enemy_positions, &size, battle);
+    let own_ants: Vec<(Position, u8)> = own_active.iter().map(|p| (p.clone(), 0)).collect();
+    let own_frozen_ants: Vec<(Position, u8)> = own_frozen.iter().map(|p| (p.clone(), 0)).collect();
+    let own_moves = joint_moves(&own_ants, &own_frozen_ants, &water, &size);
+
+    let (enemy_active, enemy_frozen) = split_active(&enemy_positions, &cluster.own, &size, battle);
+    let owner_of = |p: &Position| {
+        cluster
+            .enemies
+            .iter()
+            .find(|(e, _)| e == p)
+            .map_or(1, |(_, o)| *o)
+    };
+    let enemy_ants: Vec<(Position, u8)> = enemy_active
+        .iter()
+        .map(|p| (p.clone(), owner_of(p)))
+        .collect();
+    let enemy_frozen: Vec<(Position, u8)> = enemy_frozen
+        .iter()
+        .map(|p| (p.clone(), owner_of(p)))
+        .collect();
+    let enemy_moves = joint_moves(&enemy_ants, &enemy_frozen, &water, &size);
+
+    let mut best: Option<(f64, &Vec<Direction>)> = None;
+    for own_move in own_moves.iter() {
+        if best.is_some() && start.elapsed() >= battle.time_limit {
+            break;
+        }
+        let mut ants = own_frozen_ants.clone();
+        ants.extend(moved(&own_ants, own_move, &size));
+        ants.extend(enemy_frozen.iter().cloned());
+        let fixed = ants.len();
+
+        let mut worst = f64::INFINITY;
+        for enemy_move in enemy_moves.iter() {
+            ants.truncate(fixed);
+            ants.extend(moved(&enemy_ants, enemy_move, &size));
+            worst = worst.min(outcome(
+                &ants,
+                &size,
+                params.attackradius2,
+                battle.loss_weight,
+            ));
+            if best.is_some_and(|(score, _)| worst <= score) {
+                break;
+            }
+        }
+        if best.map_or(true, |(score, _)| worst > score) {
+            best = Some((worst, own_move));
+        }
+    }
+
+    match best {
+        Some((_, dirs)) => own_active
+            .iter()
+            .zip(dirs.iter())
+            .map(|(p, dir)| p.order(*dir))
+            .chain(own_frozen.iter().map(|p| p.pause()))
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Ants closest to the other side, at most `max_ants`, and the rest.
+fn split_active(
+    ants: &[Position],
+    others: &[Position],
+    size: &Position,
+    battle: &BattleParameters,
+) -> (Vec<Position>, Vec<Position>) {
+    let mut by_distance: Vec<(i64, Position)> = ants
+        .iter()
+        .map(|p| {
+            let closest = others.iter().map(|o| p.distance2(o, size)).min();
+            (closest.unwrap_or(i64::MAX), p.clone())
+        })
+        .collect();
+    by_distance.sort();
+    let mut ants: Vec<Position> = by_distance.into_iter().map(|(_, p)| p).collect();
+    let frozen = ants.split_off(battle.max_ants.min(ants.len()));
+    (ants, frozen)
+}
+
+/// Moves for each ant, staying first, except into water and except for
+/// ants of the same owner meeting on a tile.
+fn joint_moves(
+    ants: &[(Position, u8)],
+    frozen: &[(Position, u8)],
+    water: &dyn Fn(&Position) -> bool,
+    size: &Position,
+) -> Vec<Vec<Direction>> {
+    const MOVES: [Direction; 5] = [
+        Direction::NoDirection,
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+    let mut result = vec![];
+    let mut current = vec![];
+    let mut taken: BTreeSet<(Position, u8)> = frozen.iter().cloned().collect();
+
+    fn extend(
+        index: usize,
+        ants: &[(Position, u8)],
+        water: &dyn Fn(&Position) -> bool,
+        size: &Position,
+        current: &mut Vec<Direction>,
+        taken: &mut BTreeSet<(Position, u8)>,
+        result: &mut Vec<Vec<Direction>>,
+    ) {
+        if index == ants.len() {
+            result.push(current.clone());
+            return;
+        }
+        let (p, owner) = &ants[index];
+        for dir in MOVES.iter() {
+            let target = p.order(*dir).target_pos(size);
+            let key = (target.clone(), *owner);
+            if water(&target) || taken.contains(&key) {
+                continue;
+            }
+            taken.insert(key.clone());
+            current.push(*dir);
+            extend(index + 1, ants, water, size, current, taken, result);
+            current.pop();
+            taken.remove(&key);
+        }
+    }
+    extend(0, ants, water, size, &mut current, &mut taken, &mut result);
+    result
+}
+
+fn moved<'a>(
+    ants: &'a [(Position, u8)],
+    dirs: &'a [Direction],
+    size: &'a Position,
+) -> impl Iterator<Item = (Position, u8)> + 'a {
+    ants.iter()
+        .zip(dirs.iter())
+        .map(move |((p, owner), dir)| (p.order(*dir).target_pos(size), *owner))
+}
+
+/// Enemy losses minus our weighted losses, after collisions and battle.
+fn outcome(ants: &[(Position, u8)], size: &Position, attackradius2: i64, loss_weight: f64) -> f64 {
+    let mut survivors = vec![];
+    let mut dead = vec![];
+    for (i, (p, owner)) in ants.iter().enumerate() {
+        if ants.iter().enumerate().any(|(j, (q, _))| i != j && p == q) {
+            dead.push(*owner);
+        } else {
+            survivors.push((p.clone(), *owner));
+        }
+    }
+    for (i, died) in casualties(&survivors, size, attackradius2)
+        .into_iter()
+        .enumerate()
+    {
+        if died {
+            dead.push(survivors[i].1);
+        }
+    }
+    dead.iter()
+        .map(|owner| if *owner == 0 { -loss_weight } else { 1.0 })
+        .sum()
+}
+
+/// Focus battle: an ant dies if it has at least as many enemies in attack
+/// range as any of those enemies has.
+fn casualties(ants: &[(Position, u8)], size: &Position, attackradius2: i64) -> Vec<bool> {
+    let enemies: Vec<Vec<usize>> = ants
+        .iter()
+        .map(|(p, owner)| {
+            ants.iter()
+                .enumerate()
+                .filter(|(_, (q, o))| o != owner && p.distance2(q, size) <= attackradius2)
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect();
+    enemies
+        .iter()
+        .map(|own_enemies| {
+            own_enemies
+                .iter()
+                .any(|enemy| enemies[*enemy].len() <= own_enemies.len())
+        })
+        .collect()
+}
+
+fn size(params: &GameParameters) -> Position {
+    pos(params.rows as u16, params.cols as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::scenario::{order, Scenario};
+    use super::*;
+    use indoc::indoc;
+
+    fn search(s: &Scenario) -> Orders {
+        let memory = s.memory();
+        let mut orders = battle_orders(&s.world, &memory, &s.params, &BattleParameters::default());
+        orders.sort();
+        orders
+    }
+
+    #[test]
+    fn clusters_by_reach() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                a..b..........
+                ..............
+                a.............
+                .....a....a...
+                ...........c..
+                ..............
+                "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+
+        let clusters = clusters(&s.world, &s.params);
+
+        assert_eq!(
+            vec![
+                Cluster {
+                    own: vec![pos(0, 0), pos(2, 0)],
+                    enemies: vec![(pos(0, 3), 1)],
+                },
+                Cluster {
+                    own: vec![pos(3, 10)],
+                    enemies: vec![(pos(4, 11), 2)],
+                },
+            ],
+            clusters
+        );
+    }
+
+    #[test]
+    fn focus_rule() {
+        let size = pos(10, 10);
+        let ants = vec![
+            (pos(0, 0), 0),
+            (pos(0, 2), 0),
+            (pos(0, 1), 1),
+            (pos(5, 5), 2),
+        ];
+
+        assert_eq!(vec![false, false, true, false], casualties(&ants, &size, 1));
+        assert_eq!(1.0, outcome(&ants, &size, 1, 1.5));
+        assert_eq!(
+            -3.0,
+            outcome(&[(pos(1, 1), 0), (pos(1, 1), 0)], &size, 1, 1.5)
+        );
+    }
+
+    #[test]
+    fn retreats_from_even_trade() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                %%%%%%%
+                ..a.b..
+                %%%%%%%
+                "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+
+        assert_eq!(vec![order("o 1 2 W")], search(&s));
+    }
+
+    #[test]
+    fn remembered_water_blocks_retreat() {
+        // Water was only sent in an earlier turn
+        let earlier = Scenario::with_params(
+            indoc!(
+                "
+                %%%%%%%
+                .%a.b..
+                %%%%%%%
+                "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                .......
+                ..a.b..
+                .......
+                "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+
+        let orders = battle_orders(
+            &s.world,
+            &earlier.memory(),
+            &s.params,
+            &BattleParameters::default(),
+        );
+
+        assert_eq!(vec![pos(1, 2).pause()], orders);
+    }
+
+    #[test]
+    fn two_attack_trapped_enemy() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                ..a....
+                .......
+                ...%...
+                a.%b%..
+                ...%...
+                .......
+                .......
+                "
+            ),
+            |p| p.attackradius2 = 5,
+        );
+
+        assert_eq!(vec![order("o 0 2 S"), order("o 3 0 E")], search(&s));
+    }
+
+    #[test]
+    fn tries_one_move_without_time() {
+        let s = Scenario::with_params("a.b....", |p| p.attackradius2 = 1);
+        let battle = BattleParameters {
+            time_limit: Duration::from_millis(0),
+            ..BattleParameters::default()
+        };
+        let cluster = &clusters(&s.world, &s.params)[0];
+
+        assert_eq!(
+            vec![pos(0, 0).pause()],
+            best_orders(cluster, &s.world, &s.memory(), &s.params, &battle)
+        );
+    }
+}
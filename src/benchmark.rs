@@ -0,0 +1,341 @@
+//! Stress test for agent performance: runs an agent on generated worlds of
+//! increasing size and ant count, and reports `make_turn` latencies
+//! against the turn time.
+
+use super::mapgen::{generate, MapGenParameters};
+use super::process_bot::{BotStatus, ProcessBot};
+use super::rng::Rng;
+use super::tournament::{Bot, BotKind};
+use super::{Agent, GameParameters, WorldState};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Map size and number of own ants for one part of the benchmark.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct BenchmarkCase {
+    pub rows: u16,
+    pub cols: u16,
+    pub ants: usize,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct BenchmarkParameters {
+    pub cases: Vec<BenchmarkCase>,
+    pub turns_per_case: u32,
+    /// Parameters given to the agent. Map size is taken from each case.
+    pub game: GameParameters,
+    pub seed: i64,
+}
+
+impl Default for BenchmarkParameters {
+    fn default() -> Self {
+        let sizes = [(40, 40), (80, 80), (120, 120), (160, 160)];
+        let ants = [10, 100, 500, 1000];
+        BenchmarkParameters {
+            cases: sizes
+                .iter()
+                .zip(ants.iter())
+                .map(|(&(rows, cols), &ants)| BenchmarkCase { rows, cols, ants })
+                .collect(),
+            turns_per_case: 20,
+            game: GameParameters::official(),
+            seed: 0,
+        }
+    }
+}
+
+/// Latencies measured for one case.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CaseReport {
+    pub case: BenchmarkCase,
+    /// Time taken by `make_turn`, per turn.
+    pub latencies: Vec<Duration>,
+    /// Turns, starting from 1, that took longer than the turn time.
+    pub timeouts: Vec<u32>,
+    /// Turn in which an external bot crashed, 0 while preparing. The case
+    /// stops there.
+    pub crashed: Option<u32>,
+}
+
+impl CaseReport {
+    /// Latency below which the given percentage of turns were made,
+    /// 0 to 100.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        if sorted.is_empty() {
+            return Duration::default();
+        }
+        let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct BenchmarkReport {
+    pub turntime_ms: i64,
+    pub cases: Vec<CaseReport>,
+}
+
+impl BenchmarkReport {
+    /// Number of turns over the turn time in all cases.
+    pub fn timeouts(&self) -> usize {
+        self.cases.iter().map(|c| c.timeouts.len()).sum()
+    }
+
+    /// Number of cases in which the bot crashed.
+    pub fn crashes(&self) -> usize {
+        self.cases.iter().filter(|c| c.crashed.is_some()).count()
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "{:>9} {:>6} {:>6} {:>9} {:>9} {:>9} {:>9} {:>8} {:>8}",
+            "size", "ants", "turns", "p50 ms", "p90 ms", "p99 ms", "max ms", "timeouts", "crashed"
+        )?;
+        for c in self.cases.iter() {
+            writeln!(
+                f,
+                "{:>9} {:>6} {:>6} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>8} {:>8}",
+                format!("{}x{}", c.case.rows, c.case.cols),
+                c.case.ants,
+                c.latencies.len(),
+                ms(c.percentile(50.0)),
+                ms(c.percentile(90.0)),
+                ms(c.percentile(99.0)),
+                ms(c.percentile(100.0)),
+                c.timeouts.len(),
+                c.crashed.map_or("-".to_string(), |turn| turn.to_string())
+            )?;
+        }
+        write!(
+            f,
+            "{} turns over the turn time of {} ms",
+            self.timeouts(),
+            self.turntime_ms
+        )
+    }
+}
+
+/// Run the benchmark with a fresh agent for every case.
+///
+/// Each turn the agent gets a random world on a generated map, with its
+/// ants, half as many enemy ants and a quarter as much food on random land
+/// tiles. All water is sent on the first turn, as it would be on a fully
+/// visible map.
+///
+/// External bots are waited for past the turn time, so that their real
+/// latencies are measured. A case stops when the bot crashes.
+pub fn run_benchmark(bot: &Bot, params: &BenchmarkParameters) -> Result<BenchmarkReport, String> {
+    let mut cases = vec![];
+    for (index, case) in params.cases.iter().enumerate() {
+        let seed = params.seed.wrapping_add(index as i64);
+        let map = generate(&MapGenParameters {
+            rows: case.rows,
+            cols: case.cols,
+            seed,
+            ..MapGenParameters::default()
+        })?;
+        let mut water = WorldState::default();
+        let mut land = vec![];
+        for p in map.positions() {
+            if map.get(&p).is_passable() {
+                land.push(p);
+            } else {
+                water = water.water(p);
+            }
+        }
+        let hills = map.hills();
+
+        let game = GameParameters {
+            rows: case.rows as i64,
+            cols: case.cols as i64,
+            ..params.game.clone()
+        };
+        let mut subject = Subject::new(bot)?;
+        subject.agent().prepare(game);
+
+        let mut rng = Rng::new(seed);
+        let mut latencies = vec![];
+        let mut timeouts = vec![];
+        let mut crashed = if subject.is_alive() { None } else { Some(0) };
+        for turn in 1..=params.turns_per_case {
+            if crashed.is_some() {
+                break;
+            }
+            rng.shuffle(&mut land);
+            let mut world = if turn == 1 {
+                water.clone()
+            } else {
+                WorldState::default()
+            };
+            for (player, player_hills) in hills.iter().enumerate() {
+                for h in player_hills {
+                    world = world.hill(h.clone(), player as u8);
+                }
+            }
+            let enemies = case.ants / 2;
+            let foods = case.ants / 4;
+            for (i, p) in land.iter().take(case.ants + enemies + foods).enumerate() {
+                world = if i < case.ants {
+                    world.live_ant(p.clone(), 0)
+                } else if i < case.ants + enemies {
+                    world.live_ant(p.clone(), 1)
+                } else {
+                    world.food(p.clone())
+                };
+            }
+
+            let start = Instant::now();
+            subject.agent().make_turn(&world, turn);
+            let latency = start.elapsed();
+            if !subject.is_alive() {
+                crashed = Some(turn);
+                break;
+            }
+            if params.game.turntime_ms > 0
+                && latency > Duration::from_millis(params.game.turntime_ms as u64)
+            {
+                timeouts.push(turn);
+            }
+            latencies.push(latency);
+        }
+        cases.push(CaseReport {
+            case: *case,
+            latencies,
+            timeouts,
+            crashed,
+        });
+    }
+    Ok(BenchmarkReport {
+        turntime_ms: params.game.turntime_ms,
+        cases,
+    })
+}
+
+/// Agent under test, keeping external bots apart to tell when they crash.
+enum Subject {
+    InProcess(Box<dyn Agent>),
+    Process(ProcessBot),
+}
+
+impl Subject {
+    fn new(bot: &Bot) -> Result<Self, String> {
+        match &bot.kind {
+            BotKind::Command(command_line) => ProcessBot::spawn(command_line, None)
+                .map(|process| Subject::Process(process.without_time_limits()))
+                .map_err(|e| format!("Could not start bot {}: {}", bot.name, e)),
+            BotKind::InProcess(_) => bot.create_agent().map(Subject::InProcess),
+        }
+    }
+
+    fn agent(&mut self) -> &mut dyn Agent {
+        match self {
+            Subject::InProcess(agent) => agent.as_mut(),
+            Subject::Process(process) => process,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            Subject::InProcess(_) => true,
+            Subject::Process(process) => process.status() == BotStatus::Alive,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Agent, Orders};
+    use super::*;
+    use std::thread;
+
+    /// Takes longer the more ants there are.
+    struct Slow;
+
+    impl Agent for Slow {
+        fn prepare(&mut self, _params: GameParameters) {}
+
+        fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+            let ants = world.live_ants_for_player(0).len() as u64;
+            thread::sleep(Duration::from_millis(ants));
+            vec![]
+        }
+    }
+
+    #[test]
+    fn flags_slow_turns() {
+        let params = BenchmarkParameters {
+            cases: vec![
+                BenchmarkCase {
+                    rows: 20,
+                    cols: 20,
+                    ants: 1,
+                },
+                BenchmarkCase {
+                    rows: 30,
+                    cols: 30,
+                    ants: 40,
+                },
+            ],
+            turns_per_case: 3,
+            game: GameParameters {
+                turntime_ms: 20,
+                ..GameParameters::official()
+            },
+            seed: 1,
+        };
+        let report = run_benchmark(&Bot::in_process("slow", || Box::new(Slow)), &params).unwrap();
+
+        assert_eq!(2, report.cases.len());
+        assert!(report.cases[0].timeouts.is_empty());
+        assert_eq!(vec![1, 2, 3], report.cases[1].timeouts);
+        assert_eq!(3, report.timeouts());
+        assert!(report.cases[1].percentile(50.0) >= Duration::from_millis(40));
+        assert!(report
+            .to_string()
+            .contains("3 turns over the turn time of 20 ms"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_at_crash() {
+        let params = BenchmarkParameters {
+            cases: vec![BenchmarkCase {
+                rows: 20,
+                cols: 20,
+                ants: 1,
+            }],
+            ..BenchmarkParameters::default()
+        };
+
+        let report = run_benchmark(&Bot::command("quitter", "false"), &params).unwrap();
+
+        assert_eq!(Some(0), report.cases[0].crashed);
+        assert!(report.cases[0].latencies.is_empty());
+        assert_eq!(1, report.crashes());
+    }
+
+    #[test]
+    fn percentile_nearest_rank() {
+        let report = CaseReport {
+            case: BenchmarkCase {
+                rows: 1,
+                cols: 1,
+                ants: 1,
+            },
+            latencies: (1..=10).rev().map(Duration::from_millis).collect(),
+            timeouts: vec![],
+            crashed: None,
+        };
+
+        assert_eq!(Duration::from_millis(5), report.percentile(50.0));
+        assert_eq!(Duration::from_millis(9), report.percentile(90.0));
+        assert_eq!(Duration::from_millis(10), report.percentile(99.0));
+        assert_eq!(Duration::from_millis(1), report.percentile(0.0));
+    }
+}
@@ -0,0 +1,89 @@
+//! Measure how long a bot takes per turn on increasingly large worlds.
+//!
+//! ```text
+//! ants_benchmark --turntime 500 --ants 10,100,1000 ./MyBot
+//! ```
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::benchmark::{run_benchmark, BenchmarkCase, BenchmarkParameters};
+use ants_ai_challenge_api::tournament::Bot;
+use std::process::exit;
+
+const USAGE: &str = "Usage: ants_benchmark [OPTIONS] COMMAND...
+
+Runs the bot command and exits with status 1 if any turn took longer than
+the turn time or the bot crashed. The bot is waited for past the turn time.
+
+Options:
+  --sizes N,N,...     Square map sizes, one per case.
+  --ants N,N,...      Own ants, one per case.
+  --turns N           Turns per case.
+  --turntime MS       Time per turn for the bot.
+  --seed N            Seed for the generated worlds.
+";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(2)
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{} needs a numeric value", flag)))
+}
+
+fn numbers<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Vec<T> {
+    value
+        .and_then(|v| v.split(',').map(|n| n.trim().parse().ok()).collect())
+        .unwrap_or_else(|| fail(&format!("{} needs comma separated numbers", flag)))
+}
+
+fn main() {
+    let mut params = BenchmarkParameters::default();
+    let mut sizes: Vec<u16> = params.cases.iter().map(|c| c.rows).collect();
+    let mut ants: Vec<usize> = params.cases.iter().map(|c| c.ants).collect();
+    let mut command = vec![];
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sizes" => sizes = numbers(&arg, args.next()),
+            "--ants" => ants = numbers(&arg, args.next()),
+            "--turns" => params.turns_per_case = number(&arg, args.next()),
+            "--turntime" => params.game.turntime_ms = number(&arg, args.next()),
+            "--seed" => params.seed = number(&arg, args.next()),
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return;
+            }
+            flag if flag.starts_with("--") && command.is_empty() => {
+                fail(&format!("Unknown option {}", flag))
+            }
+            word => command.push(word.to_string()),
+        }
+    }
+    if command.is_empty() {
+        fail("A bot command is needed");
+    }
+    if sizes.is_empty() || sizes.len() != ants.len() {
+        fail("--sizes and --ants need the same number of values");
+    }
+    params.cases = sizes
+        .iter()
+        .zip(ants.iter())
+        .map(|(&size, &ants)| BenchmarkCase {
+            rows: size,
+            cols: size,
+            ants,
+        })
+        .collect();
+
+    let command = command.join(" ");
+    let report =
+        run_benchmark(&Bot::command(&command, &command), &params).unwrap_or_else(|e| fail(&e));
+    println!("{}", report);
+    if report.timeouts() > 0 || report.crashes() > 0 {
+        exit(1);
+    }
+}
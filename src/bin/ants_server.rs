@@ -0,0 +1,92 @@
+//! Run a game server that bots connect to over TCP, as the community
+//! servers of the challenge did.
+//!
+//! ```text
+//! ants_server --replays replays --ratings ratings.txt maps/*.map
+//! ```
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::tcp_server::{GameServer, ServerParameters};
+use ants_ai_challenge_api::Map;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::exit;
+use std::thread;
+
+const USAGE: &str = "Usage: ants_server [OPTIONS] MAP_FILE...
+
+Options:
+  --port N            Port for bots to connect to, 2081 by default.
+  --status-port N     Port of the HTTP status page, 2080 by default.
+  --turns N           Turns per game.
+  --turntime MS       Time per turn for bots.
+  --loadtime MS       Time for bots to start up.
+  --seed N            Seed for food placement.
+  --replays DIR       Write an HTML replay of every game into the directory.
+  --ratings FILE      Keep TrueSkill and Elo ratings in the file.
+";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(2)
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{} needs a numeric value", flag)))
+}
+
+fn main() {
+    let mut params = ServerParameters::new(vec![]);
+    let mut port: u16 = 2081;
+    let mut status_port: u16 = 2080;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = number(&arg, args.next()),
+            "--status-port" => status_port = number(&arg, args.next()),
+            "--turns" => params.game.turns = number(&arg, args.next()),
+            "--turntime" => params.game.turntime_ms = number(&arg, args.next()),
+            "--loadtime" => params.game.loadtime_ms = number(&arg, args.next()),
+            "--seed" => params.game.player_seed = number(&arg, args.next()),
+            "--replays" => params.replay_dir = args.next().map(PathBuf::from),
+            "--ratings" => params.ratings_file = args.next().map(PathBuf::from),
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return;
+            }
+            flag if flag.starts_with("--") => fail(&format!("Unknown option {}", flag)),
+            file => {
+                let text = std::fs::read_to_string(file)
+                    .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", file, e)));
+                params.maps.push(
+                    Map::parse(&text).unwrap_or_else(|e| fail(&format!("Bad map {}: {}", file, e))),
+                );
+            }
+        }
+    }
+    if let Some(dir) = params.replay_dir.as_ref() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            fail(&format!("Could not create {}: {}", dir.display(), e));
+        }
+    }
+
+    let server = GameServer::new(params).unwrap_or_else(|e| fail(&e));
+    let players = TcpListener::bind(("0.0.0.0", port))
+        .unwrap_or_else(|e| fail(&format!("Could not listen on port {}: {}", port, e)));
+    let status = TcpListener::bind(("0.0.0.0", status_port))
+        .unwrap_or_else(|e| fail(&format!("Could not listen on port {}: {}", status_port, e)));
+
+    let status_server = server.clone();
+    thread::spawn(move || status_server.serve_status(status));
+    println!(
+        "Bots connect on port {}, status page on port {}",
+        port, status_port
+    );
+    if let Err(e) = server.serve_players(players) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,145 @@
+//! Run a local tournament between external bots.
+//!
+//! ```text
+//! ants_tournament --bot "v1=python3 v1/MyBot.py" --bot "v2=./v2/bot" maps/*.map
+//! ```
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::rating::{RatingParameters, Ratings};
+use ants_ai_challenge_api::tournament::{run_tournament, Bot, Pairing, TournamentParameters};
+use ants_ai_challenge_api::Map;
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "Usage: ants_tournament [OPTIONS] --bot NAME=COMMAND... MAP_FILE...
+
+Options:
+  --bot NAME=COMMAND  Bot to take part, repeatable. The name is optional.
+  --swiss ROUNDS      Swiss pairing for given rounds, instead of round-robin.
+  --threads N         Games played in parallel.
+  --turns N           Turns per game.
+  --turntime MS       Time per turn for bots.
+  --loadtime MS       Time for bots to start up.
+  --seed N            Seed for food placement.
+  --replays DIR       Write an HTML replay of every game into the directory.
+  --ratings FILE      Update TrueSkill and Elo ratings kept in the file.
+";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(2)
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{} needs a numeric value", flag)))
+}
+
+fn main() {
+    let mut params = TournamentParameters::default();
+    let mut bots = vec![];
+    let mut maps = vec![];
+    let mut replays = None;
+    let mut ratings_file = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bot" => {
+                let spec = args.next().unwrap_or_else(|| fail("--bot needs a command"));
+                bots.push(match spec.find('=') {
+                    Some(i) => Bot::command(&spec[..i], &spec[i + 1..]),
+                    None => Bot::command(&spec, &spec),
+                });
+            }
+            "--swiss" => {
+                params.pairing = Pairing::Swiss {
+                    rounds: number(&arg, args.next()),
+                }
+            }
+            "--threads" => params.threads = number(&arg, args.next()),
+            "--turns" => params.game.turns = number(&arg, args.next()),
+            "--turntime" => params.game.turntime_ms = number(&arg, args.next()),
+            "--loadtime" => params.game.loadtime_ms = number(&arg, args.next()),
+            "--seed" => params.game.player_seed = number(&arg, args.next()),
+            "--replays" => replays = args.next(),
+            "--ratings" => ratings_file = args.next(),
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return;
+            }
+            flag if flag.starts_with("--") => fail(&format!("Unknown option {}", flag)),
+            file => {
+                let text = std::fs::read_to_string(file)
+                    .unwrap_or_else(|e| fail(&format!("Could not read {}: {}", file, e)));
+                maps.push(
+                    Map::parse(&text).unwrap_or_else(|e| fail(&format!("Bad map {}: {}", file, e))),
+                );
+            }
+        }
+    }
+    if bots.is_empty() || maps.is_empty() {
+        fail("At least one bot and one map is needed");
+    }
+
+    let result = run_tournament(&bots, &maps, &params);
+
+    for error in result.errors.iter() {
+        eprintln!("{}", error);
+    }
+    if let Some(dir) = replays {
+        for (i, (game, game_result)) in result.games.iter().enumerate() {
+            let names: Vec<&str> = game.seats.iter().map(|b| bots[*b].name.as_str()).collect();
+            let file = Path::new(&dir).join(format!("game_{:04}_{}.html", i, names.join("_")));
+            if let Err(e) = std::fs::write(&file, game_result.replay.to_html()) {
+                eprintln!("Could not write {}: {}", file.display(), e);
+            }
+        }
+    }
+
+    if let Some(file) = ratings_file {
+        let path = Path::new(&file);
+        let mut ratings =
+            Ratings::load(path, RatingParameters::default()).unwrap_or_else(|e| fail(&e));
+        for (game, game_result) in result.games.iter() {
+            let names: Vec<&str> = game.seats.iter().map(|b| bots[*b].name.as_str()).collect();
+            ratings.update_with_score(&names, &game_result.score);
+        }
+        if let Err(e) = ratings.save(path) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+        }
+        println!(
+            "{:>4}  {:<20} {:>6} {:>8} {:>8} {:>8}",
+            "rank", "bot", "games", "mu", "sigma", "elo"
+        );
+        for (rank, (name, rating)) in ratings.leaderboard().iter().enumerate() {
+            println!(
+                "{:>4}  {:<20} {:>6} {:>8.2} {:>8.2} {:>8.0}",
+                rank + 1,
+                name,
+                rating.games,
+                rating.mu,
+                rating.sigma,
+                rating.elo
+            );
+        }
+        println!();
+    }
+
+    println!(
+        "{:>4}  {:<20} {:>6} {:>8} {:>8} {:>8}",
+        "rank", "bot", "games", "points", "score", "timeouts"
+    );
+    for (rank, standing) in result.standings.iter().enumerate() {
+        println!(
+            "{:>4}  {:<20} {:>6} {:>8.1} {:>8} {:>8}",
+            rank + 1,
+            standing.name,
+            standing.games,
+            standing.points,
+            standing.total_score,
+            standing.timeouts
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Sets of tiles with one bit per tile, for fast visibility, threat and
+//! combat calculations on the wrapped map.
+
+use super::{pos, Direction, Position};
+
+/// Set of tiles of a wrapped map. Each row is stored in its own 64 bit
+/// words, column `c` in bit `c % 64` of word `c / 64`, and the bits past
+/// the last column are always clear.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct BitGrid {
+    size: Position,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    /// Empty set for a map of given size, rows and columns.
+    pub fn new(size: Position) -> Self {
+        let words_per_row = (size.col as usize).div_ceil(64);
+        BitGrid {
+            words: vec![0; size.row as usize * words_per_row],
+            words_per_row,
+            size,
+        }
+    }
+
+    /// Set of the given tiles.
+    pub fn with_positions(size: Position, positions: &[Position]) -> Self {
+        let mut grid = BitGrid::new(size);
+        for p in positions {
+            grid.set(p, true);
+        }
+        grid
+    }
+
+    pub fn size(&self) -> &Position {
+        &self.size
+    }
+
+    /// Whether the tile is in the set. Panics if it is outside the map.
+    pub fn get(&self, p: &Position) -> bool {
+        let (word, bit) = self.locate(p);
+        self.words[word] & bit != 0
+    }
+
+    /// Add or remove the tile. Panics if it is outside the map.
+    pub fn set(&mut self, p: &Position, value: bool) {
+        let (word, bit) = self.locate(p);
+        if value {
+            self.words[word] |= bit;
+        } else {
+            self.words[word] &= !bit;
+        }
+    }
+
+    /// Number of tiles in the set.
+    pub fn count(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    /// Tiles in either set. Both must be of the same size.
+    pub fn union(&self, other: &BitGrid) -> BitGrid {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Tiles in both sets. Both must be of the same size.
+    pub fn intersection(&self, other: &BitGrid) -> BitGrid {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Every tile moved one step in the direction, wrapping around the
+    /// edges of the map. `NoDirection` keeps the tiles where they are.
+    pub fn shift(&self, dir: Direction) -> BitGrid {
+        let mut shifted = BitGrid::new(self.size.clone());
+        let rows = self.size.row as usize;
+        let width = self.words_per_row;
+        for row in 0..rows {
+            let from = &self.words[row * width..(row + 1) * width];
+            let target = match dir {
+                Direction::North => (row + rows - 1) % rows,
+                Direction::South => (row + 1) % rows,
+                _ => row,
+            };
+            let to = &mut shifted.words[target * width..(target + 1) * width];
+            match dir {
+                Direction::East => shift_row_east(from, to, self.size.col as usize),
+                Direction::West => shift_row_west(from, to, self.size.col as usize),
+                _ => to.copy_from_slice(from),
+            }
+        }
+        shifted
+    }
+
+    /// Tiles in the set, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = Position> + '_ {
+        self.words.iter().enumerate().flat_map(move |(i, word)| {
+            let row = (i / self.words_per_row) as u16;
+            let first_col = (i % self.words_per_row) * 64;
+            let mut bits = *word;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(pos(row, (first_col + bit) as u16))
+            })
+        })
+    }
+
+    /// Word and bit of a tile. Panics if the tile is outside the map, as its
+    /// bit would be in the padding of a row or in another row.
+    fn locate(&self, p: &Position) -> (usize, u64) {
+        assert!(
+            p.row < self.size.row && p.col < self.size.col,
+            "{:?} outside map of size {:?}",
+            p,
+            self.size
+        );
+        let col = p.col as usize;
+        (
+            p.row as usize * self.words_per_row + col / 64,
+            1 << (col % 64),
+        )
+    }
+
+    fn combine(&self, other: &BitGrid, op: impl Fn(u64, u64) -> u64) -> BitGrid {
+        assert_eq!(self.size, other.size, "Grids of different sizes");
+        BitGrid {
+            size: self.size.clone(),
+            words_per_row: self.words_per_row,
+            words: self
+                .words
+                .iter()
+                .zip(other.words.iter())
+                .map(|(a, b)| op(*a, *b))
+                .collect(),
+        }
+    }
+}
+
+/// Move the bits of a row one column up, the last column wrapping to the
+/// first.
+fn shift_row_east(from: &[u64], to: &mut [u64], cols: usize) {
+    if cols == 0 {
+        return;
+    }
+    let mut carry = 0;
+    for (f, t) in from.iter().zip(to.iter_mut()) {
+        *t = (f << 1) | carry;
+        carry = f >> 63;
+    }
+    let last = cols - 1;
+    if from[last / 64] >> (last % 64) & 1 != 0 {
+        to[0] |= 1;
+    }
+    clear_past(to, cols);
+}
+
+/// Move the bits of a row one column down, the first column wrapping to
+/// the last.
+fn shift_row_west(from: &[u64], to: &mut [u64], cols: usize) {
+    if cols == 0 {
+        return;
+    }
+    for (i, t) in to.iter_mut().enumerate() {
+        let carry = from.get(i + 1).map_or(0, |next| next << 63);
+        *t = (from[i] >> 1) | carry;
+    }
+    if from[0] & 1 != 0 {
+        let last = cols - 1;
+        to[last / 64] |= 1 << (last % 64);
+    }
+}
+
+/// Clear the bits past the last column.
+fn clear_past(row: &mut [u64], cols: usize) {
+    if cols % 64 != 0 {
+        if let Some(last) = row.last_mut() {
+            *last &= (1 << (cols % 64)) - 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::*;
+
+    #[test]
+    fn set_get_and_iterate() {
+        let mut grid = BitGrid::with_positions(pos(3, 70), &[pos(2, 69), pos(0, 1), pos(1, 64)]);
+        grid.set(&pos(0, 1), false);
+        grid.set(&pos(0, 63), true);
+
+        assert!(grid.get(&pos(1, 64)));
+        assert!(!grid.get(&pos(0, 1)));
+        assert_eq!(3, grid.count());
+        assert_eq!(
+            vec![pos(0, 63), pos(1, 64), pos(2, 69)],
+            grid.iter().collect::<Vec<_>>()
+        );
+        assert!(BitGrid::new(pos(3, 70)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside map")]
+    fn column_past_the_map() {
+        BitGrid::new(pos(3, 70)).set(&pos(0, 70), true);
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let size = pos(2, 5);
+        let a = BitGrid::with_positions(size.clone(), &[pos(0, 0), pos(1, 4)]);
+        let b = BitGrid::with_positions(size.clone(), &[pos(1, 4), pos(1, 2)]);
+
+        assert_eq!(
+            vec![pos(0, 0), pos(1, 2), pos(1, 4)],
+            a.union(&b).iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![pos(1, 4)],
+            a.intersection(&b).iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shifts_wrap_around() {
+        for cols in [5, 64, 65, 130].iter() {
+            let size = pos(4, *cols);
+            let last = *cols - 1;
+            let grid = BitGrid::with_positions(
+                size.clone(),
+                &[pos(0, 0), pos(3, last), pos(1, 63 % cols)],
+            );
+
+            for dir in [North, East, South, West, NoDirection].iter() {
+                let mut expected: Vec<Position> = grid
+                    .iter()
+                    .map(|p| p.order(*dir).target_pos(&size))
+                    .collect();
+                expected.sort();
+                let shifted = grid.shift(*dir);
+                assert_eq!(
+                    expected,
+                    shifted.iter().collect::<Vec<_>>(),
+                    "{:?} {}",
+                    dir,
+                    cols
+                );
+                assert_eq!(grid.count(), shifted.count());
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use super::Position;
+
+/// Visual debug annotation made by an agent during a turn.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Annotation {
+    /// Colour a whole tile, using a CSS colour, e.g. `"rgba(255,0,0,0.5)"`.
+    TileColor { pos: Position, color: String },
+    /// Arrow between two positions, using a CSS colour.
+    Arrow {
+        from: Position,
+        to: Position,
+        color: String,
+    },
+    /// Text label attached to a tile.
+    Label { pos: Position, text: String },
+}
+
+/// Collector of per-turn debug annotations. A disabled sink drops
+/// everything written into it, which is what the agent gets when running
+/// against the real game server.
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct DebugSink {
+    enabled: bool,
+    annotations: Vec<Annotation>,
+}
+
+impl DebugSink {
+    /// Sink that silently drops all annotations.
+    pub fn disabled() -> Self {
+        DebugSink::default()
+    }
+
+    /// Sink that keeps all annotations until taken.
+    pub fn recording() -> Self {
+        DebugSink {
+            enabled: true,
+            annotations: vec![],
+        }
+    }
+
+    /// Whether annotations are kept. Agents can use this to skip expensive
+    /// debug-only computations.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Colour a tile
+    pub fn tile_color(&mut self, pos: Position, color: &str) {
+        self.add(Annotation::TileColor {
+            pos,
+            color: color.to_string(),
+        });
+    }
+
+    /// Draw an arrow between two tiles
+    pub fn arrow(&mut self, from: Position, to: Position, color: &str) {
+        self.add(Annotation::Arrow {
+            from,
+            to,
+            color: color.to_string(),
+        });
+    }
+
+    /// Attach a text label to a tile
+    pub fn label(&mut self, pos: Position, text: &str) {
+        self.add(Annotation::Label {
+            pos,
+            text: text.to_string(),
+        });
+    }
+
+    /// Add any annotation
+    pub fn add(&mut self, annotation: Annotation) {
+        if self.enabled {
+            self.annotations.push(annotation);
+        }
+    }
+
+    /// Take all annotations made so far, leaving the sink empty.
+    pub fn take(&mut self) -> Vec<Annotation> {
+        std::mem::take(&mut self.annotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::*;
+
+    #[test]
+    fn disabled_sink_drops_annotations() {
+        let mut sink = DebugSink::disabled();
+        sink.tile_color(pos(1, 2), "red");
+        sink.label(pos(1, 2), "foo");
+
+        assert!(!sink.is_enabled());
+        assert_eq!(Vec::<Annotation>::new(), sink.take());
+    }
+
+    #[test]
+    fn recording_sink_keeps_annotations_until_taken() {
+        let mut sink = DebugSink::recording();
+        sink.arrow(pos(1, 2), pos(3, 4), "blue");
+        sink.label(pos(1, 2), "foo");
+
+        let expected = vec![
+            Annotation::Arrow {
+                from: pos(1, 2),
+                to: pos(3, 4),
+                color: String::from("blue"),
+            },
+            Annotation::Label {
+                pos: pos(1, 2),
+                text: String::from("foo"),
+            },
+        ];
+        assert_eq!(expected, sink.take());
+        assert_eq!(Vec::<Annotation>::new(), sink.take());
+    }
+}
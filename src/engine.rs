@@ -0,0 +1,637 @@
+use super::debug::DebugSink;
+use super::map::{Map, Tile};
+use super::replay::Replay;
+use super::rng::Rng;
+use super::{
+    offsets, Agent, Direction, GameParameters, Order, Orders, Position, Score, WorldState,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+/// Expected number of food rounds per turn. Each round places one food at
+/// the same offset from every player's first hill.
+const FOOD_ROUNDS_PER_TURN: f64 = 0.5;
+/// Food rounds placed before the first turn.
+const FOOD_ROUNDS_AT_START: u32 = 2;
+
+/// Outcome of a locally played game.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct GameResult {
+    /// Final score, indexed by player number in the map.
+    pub score: Score,
+    /// Number of turns played.
+    pub turns: u32,
+    /// Players whose agent exceeded the load or turn time. Their ants stay
+    /// on the map, but get no more orders.
+    pub timed_out: Vec<bool>,
+    /// Full information replay of the game.
+    pub replay: Replay,
+}
+
+impl GameResult {
+    /// Rank per player, zero being the best. Players with equal score
+    /// share the same rank.
+    pub fn ranks(&self) -> Vec<usize> {
+        let scores = &self.score.per_player;
+        scores
+            .iter()
+            .map(|a| scores.iter().filter(|b| *b > a).count())
+            .collect()
+    }
+}
+
+/// Play a complete game on the map according to the official rules, with
+/// one agent per map player.
+///
+/// Each agent sees the game from its own perspective, with itself as
+/// player 0 and the other players numbered in map order after it. Time
+/// limits from the parameters are enforced for each call to `prepare` and
+/// `make_turn`, unless they are zero or negative. Map size in the
+/// parameters is overridden by the map. Debug annotations of all agents
+/// are kept in the replay.
+pub fn play_game(map: &Map, params: &GameParameters, agents: &mut [&mut dyn Agent]) -> GameResult {
+    assert_eq!(
+        map.players as usize,
+        agents.len(),
+        "One agent per map player is needed"
+    );
+    let params = GameParameters {
+        rows: map.size.row as i64,
+        cols: map.size.col as i64,
+        ..params.clone()
+    };
+    let players = agents.len();
+    let mut game = Game::new(map, &params);
+    let mut timed_out = vec![false; players];
+    let mut replay = Replay::new(params.clone());
+    let mut debug = DebugSink::recording();
+
+    for (player, agent) in agents.iter_mut().enumerate() {
+        let start = Instant::now();
+        agent.prepare(params.clone());
+        timed_out[player] = is_late(start, params.loadtime_ms);
+    }
+
+    let mut turn = 0;
+    while (turn as i64) < params.turns && !game.is_over(&timed_out) {
+        turn += 1;
+        let mut orders: Vec<Orders> = vec![vec![]; players];
+        for (player, agent) in agents.iter_mut().enumerate() {
+            if timed_out[player] {
+                continue;
+            }
+            let world = game.player_world(player as u8);
+            let start = Instant::now();
+            let player_orders = agent.make_turn_with_debug(&world, turn, &mut debug);
+            timed_out[player] = is_late(start, params.turntime_ms);
+            if !timed_out[player] {
+                orders[player] = game.valid_orders(player as u8, &player_orders);
+            }
+        }
+        replay.push_debug_turn(game.full_world(turn == 1), orders.concat(), debug.take());
+        game.finish_turn(&orders);
+    }
+
+    if game.is_over(&timed_out) {
+        game.survivor_bonus(&timed_out);
+    }
+    let score = game.score();
+    for (player, agent) in agents.iter_mut().enumerate() {
+        let world = game.player_world(player as u8);
+        let mut player_score = score.per_player.clone();
+        player_score.rotate_left(player);
+        agent.at_end(
+            world,
+            Score {
+                per_player: player_score,
+            },
+        );
+    }
+
+    GameResult {
+        score,
+        turns: turn,
+        timed_out,
+        replay,
+    }
+}
+
+fn is_late(start: Instant, limit_ms: i64) -> bool {
+    limit_ms > 0 && start.elapsed() > Duration::from_millis(limit_ms as u64)
+}
+
+#[derive(Debug, Clone)]
+struct Hill {
+    pos: Position,
+    owner: u8,
+    razed: bool,
+}
+
+/// Full information game state, as kept by the game server.
+struct Game {
+    size: Position,
+    params: GameParameters,
+    players: u8,
+    water: Vec<bool>,
+    ants: BTreeMap<Position, u8>,
+    /// Ants that died during the last turn.
+    dead: Vec<(Position, u8)>,
+    hills: Vec<Hill>,
+    food: BTreeSet<Position>,
+    food_stock: Vec<u32>,
+    score: Vec<i64>,
+    seen_water: Vec<Vec<bool>>,
+    /// Position per player that food is placed relative to, for fairness.
+    food_anchors: Vec<Option<Position>>,
+    food_rounds: f64,
+    rng: Rng,
+}
+
+impl Game {
+    fn new(map: &Map, params: &GameParameters) -> Self {
+        let players = map.players;
+        let world = map.to_world_state();
+        let mut game = Game {
+            size: map.size.clone(),
+            params: params.clone(),
+            players,
+            water: map
+                .positions()
+                .map(|p| map.get(&p) == Tile::Water)
+                .collect(),
+            ants: BTreeMap::new(),
+            dead: vec![],
+            hills: vec![],
+            food: world.foods.iter().cloned().collect(),
+            food_stock: vec![0; players as usize],
+            score: vec![0; players as usize],
+            seen_water: vec![
+                vec![false; map.size.row as usize * map.size.col as usize];
+                players as usize
+            ],
+            food_anchors: vec![],
+            food_rounds: 0.0,
+            rng: Rng::new(params.player_seed),
+        };
+
+        for player in 0..players {
+            let hills = world.hills_for_player(player);
+            let ants = world.live_ants_for_player(player);
+            game.food_anchors
+                .push(hills.first().or_else(|| ants.first()).cloned());
+            game.score[player as usize] = hills.len() as i64;
+            for pos in hills {
+                game.hills.push(Hill {
+                    pos,
+                    owner: player,
+                    razed: false,
+                });
+            }
+            for pos in ants {
+                game.ants.insert(pos, player);
+            }
+        }
+        for _ in 0..FOOD_ROUNDS_AT_START {
+            game.spawn_food_round();
+        }
+        game
+    }
+
+    fn index(&self, p: &Position) -> usize {
+        p.row as usize * self.size.col as usize + p.col as usize
+    }
+
+    fn wrapped(&self, p: &Position, (d_row, d_col): (i64, i64)) -> Position {
+        self.size
+            .as_size_for_pos(p.row as i64 + d_row, p.col as i64 + d_col)
+    }
+
+    fn score(&self) -> Score {
+        Score {
+            per_player: self.score.iter().map(|s| (*s).max(0) as u64).collect(),
+        }
+    }
+
+    fn has_ants(&self, player: u8) -> bool {
+        self.ants.values().any(|owner| *owner == player)
+    }
+
+    /// Game is over when at most one player is left with ants to command.
+    fn is_over(&self, timed_out: &[bool]) -> bool {
+        let active = (0..self.players)
+            .filter(|p| !timed_out[*p as usize] && self.has_ants(*p))
+            .count();
+        active == 0 || (self.players > 1 && active == 1)
+    }
+
+    /// A sole survivor gets the points for all remaining enemy hills.
+    fn survivor_bonus(&mut self, timed_out: &[bool]) {
+        let survivors: Vec<u8> = (0..self.players)
+            .filter(|p| !timed_out[*p as usize] && self.has_ants(*p))
+            .collect();
+        if let [survivor] = survivors[..] {
+            for hill in self.hills.iter_mut() {
+                if !hill.razed && hill.owner != survivor {
+                    hill.razed = true;
+                    self.score[survivor as usize] += 2;
+                    self.score[hill.owner as usize] -= 1;
+                }
+            }
+        }
+    }
+
+    fn visible(&self, player: u8) -> Vec<bool> {
+        let mut visible = vec![false; self.water.len()];
+        let view = offsets(self.params.viewradius2);
+        for (pos, _) in self.ants.iter().filter(|(_, o)| **o == player) {
+            for offset in view.iter() {
+                visible[self.index(&self.wrapped(pos, *offset))] = true;
+            }
+        }
+        visible
+    }
+
+    /// What the player sees this turn. Water is only sent the first time it
+    /// is seen, as by the official server.
+    fn player_world(&mut self, player: u8) -> WorldState {
+        let visible = self.visible(player);
+        let mut world = WorldState::default();
+
+        for (i, is_water) in self.water.iter().enumerate() {
+            if *is_water && visible[i] && !self.seen_water[player as usize][i] {
+                self.seen_water[player as usize][i] = true;
+                let cols = self.size.col as usize;
+                world = world.water(super::pos((i / cols) as u16, (i % cols) as u16));
+            }
+        }
+        for food in self.food.iter().filter(|f| visible[self.index(f)]) {
+            world = world.food(food.clone());
+        }
+        for hill in self
+            .hills
+            .iter()
+            .filter(|h| !h.razed && visible[self.index(&h.pos)])
+        {
+            world = world.hill(hill.pos.clone(), hill.owner);
+        }
+        for (pos, owner) in self.ants.iter().filter(|(p, _)| visible[self.index(p)]) {
+            world = world.live_ant(pos.clone(), *owner);
+        }
+        for (pos, owner) in self.dead.iter().filter(|(p, _)| visible[self.index(p)]) {
+            world = world.dead_ant(pos.clone(), *owner);
+        }
+        world.from_perspective(player, self.players)
+    }
+
+    /// Everything on the map, with water only if asked for.
+    fn full_world(&self, with_water: bool) -> WorldState {
+        let mut world = WorldState::default();
+        if with_water {
+            for (i, _) in self.water.iter().enumerate().filter(|(_, w)| **w) {
+                let cols = self.size.col as usize;
+                world = world.water(super::pos((i / cols) as u16, (i % cols) as u16));
+            }
+        }
+        for food in self.food.iter() {
+            world = world.food(food.clone());
+        }
+        for hill in self.hills.iter().filter(|h| !h.razed) {
+            world = world.hill(hill.pos.clone(), hill.owner);
+        }
+        for (pos, owner) in self.ants.iter() {
+            world = world.live_ant(pos.clone(), *owner);
+        }
+        for (pos, owner) in self.dead.iter() {
+            world = world.dead_ant(pos.clone(), *owner);
+        }
+        world
+    }
+
+    /// Orders for the player's own ants, that do not walk into water. Only
+    /// the first order for each ant is kept.
+    fn valid_orders(&self, player: u8, orders: &[Order]) -> Orders {
+        let mut ordered = BTreeSet::new();
+        orders
+            .iter()
+            .filter(|o| {
+                o.pos.row < self.size.row
+                    && o.pos.col < self.size.col
+                    && self.ants.get(&o.pos) == Some(&player)
+                    && !self.water[self.index(&o.target_pos(&self.size))]
+                    && ordered.insert(o.pos.clone())
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn finish_turn(&mut self, orders: &[Orders]) {
+        self.dead.clear();
+        self.move_ants(orders);
+        self.attack();
+        self.raze_hills();
+        self.spawn_ants();
+        self.gather_food();
+
+        self.food_rounds += FOOD_ROUNDS_PER_TURN;
+        while self.food_rounds >= 1.0 {
+            self.food_rounds -= 1.0;
+            self.spawn_food_round();
+        }
+    }
+
+    /// Move all ants at once. Ants ending up on the same tile all die.
+    fn move_ants(&mut self, orders: &[Orders]) {
+        let directions: BTreeMap<&Position, Direction> =
+            orders.iter().flatten().map(|o| (&o.pos, o.dir)).collect();
+        let mut targets: BTreeMap<Position, Vec<u8>> = BTreeMap::new();
+
+        for (pos, owner) in self.ants.iter() {
+            let target = match directions.get(pos) {
+                Some(dir) => pos.order(*dir).target_pos(&self.size),
+                None => pos.clone(),
+            };
+            targets.entry(target).or_default().push(*owner);
+        }
+
+        self.ants.clear();
+        for (pos, owners) in targets {
+            if let [owner] = owners[..] {
+                self.ants.insert(pos, owner);
+            } else {
+                self.dead
+                    .extend(owners.into_iter().map(|owner| (pos.clone(), owner)));
+            }
+        }
+    }
+
+    /// Focus battle: an ant dies if it has at least as many enemies in
+    /// attack range as any of those enemies has.
+    fn attack(&mut self) {
+        let range = offsets(self.params.attackradius2);
+        let enemies: BTreeMap<&Position, Vec<Position>> = self
+            .ants
+            .iter()
+            .map(|(pos, owner)| {
+                let in_range = range
+                    .iter()
+                    .map(|offset| self.wrapped(pos, *offset))
+                    .filter(|p| self.ants.get(p).is_some_and(|o| o != owner))
+                    .collect();
+                (pos, in_range)
+            })
+            .collect();
+
+        let dying: Vec<Position> = enemies
+            .iter()
+            .filter(|(_, own_enemies)| {
+                own_enemies
+                    .iter()
+                    .any(|enemy| enemies[enemy].len() <= own_enemies.len())
+            })
+            .map(|(pos, _)| (*pos).clone())
+            .collect();
+
+        for pos in dying {
+            if let Some(owner) = self.ants.remove(&pos) {
+                self.dead.push((pos, owner));
+            }
+        }
+    }
+
+    fn raze_hills(&mut self) {
+        for hill in self.hills.iter_mut().filter(|h| !h.razed) {
+            if let Some(razer) = self.ants.get(&hill.pos).filter(|o| **o != hill.owner) {
+                hill.razed = true;
+                self.score[*razer as usize] += 2;
+                self.score[hill.owner as usize] -= 1;
+            }
+        }
+    }
+
+    /// Each free hill spawns one ant per turn, as long as its owner has
+    /// gathered food left.
+    fn spawn_ants(&mut self) {
+        for hill in self.hills.iter().filter(|h| !h.razed) {
+            let stock = &mut self.food_stock[hill.owner as usize];
+            if *stock > 0 && !self.ants.contains_key(&hill.pos) {
+                *stock -= 1;
+                self.ants.insert(hill.pos.clone(), hill.owner);
+            }
+        }
+    }
+
+    /// Food next to ants of a single player is gathered by that player,
+    /// food next to ants of several players is destroyed.
+    fn gather_food(&mut self) {
+        let range = offsets(self.params.spawnradius2);
+        let mut taken = vec![];
+        for food in self.food.iter() {
+            let owners: BTreeSet<u8> = range
+                .iter()
+                .filter_map(|offset| self.ants.get(&self.wrapped(food, *offset)))
+                .cloned()
+                .collect();
+            if let Some(owner) = owners.iter().next() {
+                if owners.len() == 1 {
+                    self.food_stock[*owner as usize] += 1;
+                }
+                taken.push(food.clone());
+            }
+        }
+        for food in taken {
+            self.food.remove(&food);
+        }
+    }
+
+    fn spawn_food_round(&mut self) {
+        let d_row = self.rng.below(self.size.row as u64) as i64;
+        let d_col = self.rng.below(self.size.col as u64) as i64;
+        for anchor in self.food_anchors.clone().into_iter().flatten() {
+            let p = self.wrapped(&anchor, (d_row, d_col));
+            let is_free = !self.water[self.index(&p)]
+                && !self.ants.contains_key(&p)
+                && !self.hills.iter().any(|h| h.pos == p);
+            if is_free {
+                self.food.insert(p);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::debug::Annotation;
+    use super::super::pos;
+    use super::*;
+
+    fn game(map: &str, players: u8) -> Game {
+        let rows: Vec<&str> = map.lines().collect();
+        let text = format!(
+            "rows {}\ncols {}\nplayers {}\n{}",
+            rows.len(),
+            rows[0].len(),
+            players,
+            rows.iter()
+                .map(|r| format!("m {}\n", r))
+                .collect::<String>()
+        );
+        let params = GameParameters {
+            viewradius2: 4,
+            attackradius2: 5,
+            spawnradius2: 1,
+            ..GameParameters::default()
+        };
+        let mut game = Game::new(&Map::parse(&text).unwrap(), &params);
+        game.food.clear();
+        game
+    }
+
+    fn ants(game: &Game) -> Vec<(Position, u8)> {
+        game.ants.iter().map(|(p, o)| (p.clone(), *o)).collect()
+    }
+
+    #[test]
+    fn colliding_ants_die() {
+        let mut game = game("a.a.....\n........\n........", 1);
+
+        let orders = game.valid_orders(0, &[pos(0, 0).east(), pos(0, 2).west()]);
+        game.finish_turn(&[orders]);
+
+        assert_eq!(Vec::<(Position, u8)>::new(), ants(&game));
+        assert_eq!(vec![(pos(0, 1), 0), (pos(0, 1), 0)], game.dead);
+    }
+
+    #[test]
+    fn invalid_orders_are_dropped() {
+        let game = game("a%b.....\n........\n........", 2);
+
+        let orders = [
+            pos(0, 0).east(),
+            pos(0, 2).west(),
+            pos(0, 5).west(),
+            pos(0, 0).south(),
+            pos(0, 0).north(),
+            pos(0, 0).pause(),
+        ];
+
+        assert_eq!(vec![pos(0, 0).south()], game.valid_orders(0, &orders));
+    }
+
+    #[test]
+    fn holds_are_kept() {
+        let mut game = game("a...b...\n........\n........", 2);
+        let orders = game.valid_orders(0, &[pos(0, 0).pause()]);
+
+        assert_eq!(vec![pos(0, 0).pause()], orders);
+        game.finish_turn(&[orders, vec![]]);
+        assert_eq!(Some(&0), game.ants.get(&pos(0, 0)));
+    }
+
+    #[test]
+    fn focus_battle() {
+        // One against one, both die. One against two, the single ant dies.
+        let mut game = game(
+            "a.b.......\n..........\n..........\n.....a.b..\n.......b..",
+            2,
+        );
+
+        game.finish_turn(&[vec![], vec![]]);
+
+        assert_eq!(vec![(pos(3, 7), 1), (pos(4, 7), 1)], ants(&game));
+        assert_eq!(3, game.dead.len());
+    }
+
+    #[test]
+    fn spawn_and_gather() {
+        let mut game = game("A.1.....\n........\n........\n........", 2);
+        game.food.insert(pos(2, 0));
+        game.food_stock[1] = 1;
+
+        let orders = game.valid_orders(0, &[pos(0, 0).south()]);
+        game.finish_turn(&[orders, vec![]]);
+
+        assert_eq!(vec![1, 0], game.food_stock);
+        assert!(game.food.is_empty());
+        assert_eq!(vec![(pos(0, 2), 1), (pos(1, 0), 0)], ants(&game));
+    }
+
+    #[test]
+    fn raze_hill() {
+        let mut game = game("0b......\n........", 2);
+        assert_eq!(vec![1, 0], game.score);
+
+        let orders = game.valid_orders(1, &[pos(0, 1).west()]);
+        game.finish_turn(&[vec![], orders]);
+
+        assert!(game.hills[0].razed);
+        assert_eq!(vec![0, 2], game.score);
+    }
+
+    #[test]
+    fn player_world_from_own_perspective() {
+        let mut game = game("a.b%....\n........\n........\n.......c", 3);
+
+        let world = game.player_world(1);
+
+        assert_eq!(
+            WorldState::default()
+                .water(pos(0, 3))
+                .live_ant(pos(0, 0), 2)
+                .live_ant(pos(0, 2), 0),
+            world
+        );
+        // Water is only sent once
+        assert_eq!(Vec::<Position>::new(), game.player_world(1).waters);
+    }
+
+    struct Annotator;
+
+    impl Agent for Annotator {
+        fn prepare(&mut self, _params: GameParameters) {}
+
+        fn make_turn(&mut self, _world: &WorldState, _turn_count: u32) -> Orders {
+            vec![]
+        }
+
+        fn make_turn_with_debug(
+            &mut self,
+            world: &WorldState,
+            turn_count: u32,
+            debug: &mut DebugSink,
+        ) -> Orders {
+            for ant in world.my_ants() {
+                debug.label(ant.clone(), &turn_count.to_string());
+            }
+            vec![]
+        }
+    }
+
+    #[test]
+    fn replay_keeps_debug_annotations() {
+        let map = Map::parse("rows 1\ncols 8\nplayers 2\nm a...b...\n").unwrap();
+        let params = GameParameters {
+            turns: 2,
+            ..GameParameters::default()
+        };
+
+        let result = play_game(&map, &params, &mut [&mut Annotator, &mut Annotator]);
+
+        let labels: Vec<Vec<Annotation>> = result
+            .replay
+            .turns
+            .iter()
+            .map(|t| t.debug.clone())
+            .collect();
+        let label = |p: Position, text: &str| Annotation::Label {
+            pos: p,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            vec![
+                vec![label(pos(0, 0), "1"), label(pos(0, 4), "1")],
+                vec![label(pos(0, 0), "2"), label(pos(0, 4), "2")],
+            ],
+            labels
+        );
+    }
+}
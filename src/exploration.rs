@@ -0,0 +1,173 @@
+//! Sending idle ants to the parts of the map seen least recently, spread
+//! out so that no two ants explore the same area.
+
+use super::map_memory::MapMemory;
+use super::{Direction, Orders, Position, WorldState};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, VecDeque};
+
+/// Turns since the tile was last seen, or one more than the current turn
+/// for tiles never seen. Known water has nothing to see and scores 0.
+pub fn staleness(memory: &MapMemory, p: &Position) -> u32 {
+    if memory.is_water(p) {
+        return 0;
+    }
+    match memory.last_seen(p) {
+        Some(seen) => memory.turn().saturating_sub(seen),
+        None => memory.turn() + 1,
+    }
+}
+
+/// Tiles never seen next to tiles seen, row by row.
+pub fn frontier(memory: &MapMemory) -> Vec<Position> {
+    memory
+        .map()
+        .positions()
+        .filter(|p| {
+            memory.last_seen(p).is_none()
+                && p.neighbours(memory.size())
+                    .iter()
+                    .any(|n| memory.last_seen(n).is_some() && !memory.is_water(n))
+        })
+        .collect()
+}
+
+/// First steps for the idle ants towards the stalest tiles.
+///
+/// Tiles are split between the idle ants by walking distance, with a
+/// breadth-first search from all of them at once, and each ant heads for
+/// the stalest tile of its own part, the closest one of those. Tiles never
+/// seen are assumed to be land, while known water and our ants that are
+/// not idle are in the way. Ants go to different tiles, and ants without
+/// anything to explore get no order.
+pub fn explore(memory: &MapMemory, world: &WorldState, idle: &[Position]) -> Orders {
+    let map = memory.map();
+    let size = memory.size();
+    let idle_set: BTreeSet<&Position> = idle.iter().collect();
+    let mut blocked = vec![false; size.row as usize * size.col as usize];
+    for p in map.positions().filter(|p| memory.is_water(p)) {
+        blocked[map.index(&p)] = true;
+    }
+    for ant in world.live_ants_for_player(0) {
+        if !idle_set.contains(&ant) {
+            blocked[map.index(&ant)] = true;
+        }
+    }
+
+    // Nearest idle ant, distance from it and first step towards each tile
+    let mut owner: Vec<Option<usize>> = vec![None; blocked.len()];
+    let mut distance = vec![0u32; blocked.len()];
+    let mut first_step = vec![Direction::NoDirection; blocked.len()];
+    let mut queue = VecDeque::new();
+    for (k, ant) in idle.iter().enumerate() {
+        let i = map.index(ant);
+        if owner[i].is_none() {
+            owner[i] = Some(k);
+            queue.push_back(ant.clone());
+        }
+    }
+    let mut best: Vec<Option<(u32, Reverse<u32>, Direction)>> = vec![None; idle.len()];
+    while let Some(p) = queue.pop_front() {
+        let i = map.index(&p);
+        let k = owner[i].unwrap_or_default();
+        let score = staleness(memory, &p);
+        if score > 0 {
+            let candidate = Some((score, Reverse(distance[i]), first_step[i]));
+            if candidate > best[k] {
+                best[k] = candidate;
+            }
+        }
+        for dir in Direction::ALL.iter() {
+            let n = p.order(*dir).target_pos(size);
+            let j = map.index(&n);
+            if owner[j].is_none() && !blocked[j] {
+                owner[j] = Some(k);
+                distance[j] = distance[i] + 1;
+                first_step[j] = if distance[i] == 0 {
+                    *dir
+                } else {
+                    first_step[i]
+                };
+                queue.push_back(n);
+            }
+        }
+    }
+
+    idle.iter()
+        .zip(best)
+        .filter_map(|(ant, best)| match best {
+            Some((_, _, dir)) if dir != Direction::NoDirection => Some(ant.order(dir)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::scenario::order;
+    use super::super::{pos, GameParameters};
+    use super::*;
+
+    fn remembered(cols: i64, seen: &[(u32, Position)]) -> MapMemory {
+        let mut memory = MapMemory::new(&GameParameters {
+            rows: 1,
+            cols,
+            viewradius2: 4,
+            ..GameParameters::official()
+        });
+        for (turn, ant) in seen.iter() {
+            memory.update(&WorldState::default().live_ant(ant.clone(), 0), *turn);
+        }
+        memory
+    }
+
+    #[test]
+    fn frontier_and_staleness() {
+        let memory = remembered(20, &[(1, pos(0, 3)), (4, pos(0, 9))]);
+
+        assert_eq!(vec![pos(0, 0), pos(0, 6), pos(0, 12)], frontier(&memory));
+        assert_eq!(3, staleness(&memory, &pos(0, 2)));
+        assert_eq!(0, staleness(&memory, &pos(0, 8)));
+        assert_eq!(5, staleness(&memory, &pos(0, 15)));
+    }
+
+    #[test]
+    fn idle_ants_spread_out() {
+        let memory = remembered(20, &[(1, pos(0, 9))]);
+        let world = WorldState::default()
+            .live_ant(pos(0, 9), 0)
+            .live_ant(pos(0, 10), 0);
+
+        let orders = explore(&memory, &world, &[pos(0, 9), pos(0, 10)]);
+
+        assert_eq!(vec![order("o 0 9 W"), order("o 0 10 E")], orders);
+    }
+
+    #[test]
+    fn busy_ants_are_in_the_way() {
+        let memory = remembered(20, &[(1, pos(0, 9))]);
+        let world = WorldState::default()
+            .live_ant(pos(0, 8), 0)
+            .live_ant(pos(0, 9), 0);
+
+        let orders = explore(&memory, &world, &[pos(0, 9)]);
+
+        assert_eq!(vec![order("o 0 9 E")], orders);
+    }
+
+    #[test]
+    fn stalest_tiles_first() {
+        let memory = remembered(10, &[(1, pos(0, 2)), (5, pos(0, 7)), (6, pos(0, 5))]);
+        let world = WorldState::default().live_ant(pos(0, 5), 0);
+
+        assert_eq!(
+            vec![order("o 0 5 W")],
+            explore(&memory, &world, &[pos(0, 5)])
+        );
+
+        // Nothing left to see
+        let memory = remembered(5, &[(3, pos(0, 2))]);
+        let world = WorldState::default().live_ant(pos(0, 2), 0);
+        assert!(explore(&memory, &world, &[pos(0, 2)]).is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+//! Sending gatherer ants to food: each food gets at most one of our ants,
+//! keeping the total walking distance small.
+
+use super::map::Map;
+use super::map_memory::MapMemory;
+use super::{Direction, GameParameters, Orders, Position, WorldState};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::slice;
+
+/// How to find the assignment.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum AssignmentMode {
+    /// Repeatedly pair the closest food and ant. Sorts the n food and ant
+    /// pairs in O(n log n), but may walk further than needed.
+    Greedy,
+    /// Minimal total distance with the Hungarian method, in O(k³) for k
+    /// foods or ants, whichever there are more of.
+    Optimal,
+}
+
+/// Ant sent to food, with the tile it walks to, the nearest one the food is
+/// gathered from, and its walking distance there.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct Assignment {
+    pub food: Position,
+    pub ant: Position,
+    pub target: Position,
+    pub distance: u32,
+}
+
+/// Assign our ants to food, as many as can reach food, each food to at most
+/// one ant. Food is gathered by an ant within `spawnradius2`, so ants
+/// already that close have distance 0, and food within that radius of the
+/// target of an assigned ant gets no ant of its own. Ants freed that way
+/// are assigned again to the food left.
+///
+/// Takes a breadth-first search over the map for every food and every
+/// assigned ant, on top of the time of the mode. Ants walk around water,
+/// of the world or seen in earlier turns as remembered.
+pub fn assign_food(
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+    mode: AssignmentMode,
+) -> Vec<Assignment> {
+    let map = memory.water_map(world);
+    let ants = world.live_ants_for_player(0);
+    let gather: Vec<Vec<Position>> = world
+        .foods
+        .iter()
+        .map(|f| gather_tiles(&map, f, params.spawnradius2))
+        .collect();
+    let distances: Vec<Vec<Option<u32>>> = gather
+        .iter()
+        .map(|tiles| {
+            let all = map.distances_from(tiles);
+            ants.iter().map(|a| all[map.index(a)]).collect()
+        })
+        .collect();
+    let gathers = |target: &Position, food: &Position| {
+        target.distance2(food, &map.size) <= params.spawnradius2
+    };
+
+    let mut open_foods = vec![true; world.foods.len()];
+    let mut free_ants = vec![true; ants.len()];
+    let mut assignments = vec![];
+    loop {
+        let open: Vec<Vec<Option<u32>>> = distances
+            .iter()
+            .enumerate()
+            .map(|(food, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(ant, d)| d.filter(|_| open_foods[food] && free_ants[ant]))
+                    .collect()
+            })
+            .collect();
+        let pairs = match mode {
+            AssignmentMode::Greedy => greedy(&open),
+            AssignmentMode::Optimal => optimal(&open),
+        };
+        if pairs.is_empty() {
+            return assignments;
+        }
+        // Closest first, so that food covered by a closer ant is dropped
+        for (food, ant) in pairs {
+            if !open_foods[food] {
+                continue;
+            }
+            let from_ant = map.distances_from(slice::from_ref(&ants[ant]));
+            let covered = |t: &Position| {
+                world
+                    .foods
+                    .iter()
+                    .enumerate()
+                    .filter(|(f, p)| open_foods[*f] && gathers(t, p))
+                    .count()
+            };
+            let target = gather[food]
+                .iter()
+                .filter_map(|t| from_ant[map.index(t)].map(|d| (d, Reverse(covered(t)), t)))
+                .min()
+                .map(|(_, _, t)| t.clone())
+                .unwrap_or_else(|| world.foods[food].clone());
+            for (f, p) in world.foods.iter().enumerate() {
+                if gathers(&target, p) {
+                    open_foods[f] = false;
+                }
+            }
+            open_foods[food] = false;
+            free_ants[ant] = false;
+            assignments.push(Assignment {
+                food: world.foods[food].clone(),
+                ant: ants[ant].clone(),
+                target,
+                distance: distances[food][ant].unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// First steps of our ants towards the targets of their assigned food. Ants
+/// close enough to gather hold, and no two ants move onto the same tile or onto one of
+/// our ants that does not move.
+pub fn food_assignment(
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+    mode: AssignmentMode,
+) -> Orders {
+    let map = memory.water_map(world);
+    let mut taken: BTreeSet<Position> = world.live_ants_for_player(0).into_iter().collect();
+    let mut orders = vec![];
+    for assignment in assign_food(world, memory, params, mode) {
+        if assignment.distance == 0 {
+            orders.push(assignment.ant.pause());
+            continue;
+        }
+        let distances = map.distances_from(slice::from_ref(&assignment.target));
+        let step = Direction::ALL
+            .iter()
+            .map(|d| assignment.ant.order(*d))
+            .find(|o| {
+                let target = o.target_pos(&map.size);
+                distances[map.index(&target)] == Some(assignment.distance - 1)
+                    && !taken.contains(&target)
+            });
+        if let Some(order) = step {
+            taken.remove(&assignment.ant);
+            taken.insert(order.target_pos(&map.size));
+            orders.push(order);
+        }
+    }
+    orders
+}
+
+/// Land tiles from which the food is gathered.
+fn gather_tiles(map: &Map, food: &Position, spawnradius2: i64) -> Vec<Position> {
+    map.positions()
+        .filter(|p| p.distance2(food, &map.size) <= spawnradius2 && map.get(p).is_passable())
+        .collect()
+}
+
+/// Pairs of food and ant indexes, closest first.
+fn greedy(distances: &[Vec<Option<u32>>]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(u32, usize, usize)> = distances
+        .iter()
+        .enumerate()
+        .flat_map(|(food, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(ant, d)| d.map(|d| (d, food, ant)))
+        })
+        .collect();
+    candidates.sort();
+
+    let mut used_foods = BTreeSet::new();
+    let mut used_ants = BTreeSet::new();
+    let mut pairs = vec![];
+    for (_, food, ant) in candidates {
+        if !used_foods.contains(&food) && !used_ants.contains(&ant) {
+            used_foods.insert(food);
+            used_ants.insert(ant);
+            pairs.push((food, ant));
+        }
+    }
+    pairs
+}
+
+/// Cost of an unreachable pair, above any total of real distances.
+const UNREACHABLE: i64 = 1 << 40;
+
+/// Pairs of food and ant indexes with minimal total distance.
+fn optimal(distances: &[Vec<Option<u32>>]) -> Vec<(usize, usize)> {
+    let foods = distances.len();
+    let ants = distances.first().map_or(0, |row| row.len());
+    if foods == 0 || ants == 0 {
+        return vec![];
+    }
+    let cost = |food: usize, ant: usize| distances[food][ant].map_or(UNREACHABLE, i64::from);
+
+    // The Hungarian method needs at most as many rows as columns
+    let mut pairs: Vec<(usize, usize)> = if foods <= ants {
+        let matrix: Vec<Vec<i64>> = (0..foods)
+            .map(|f| (0..ants).map(|a| cost(f, a)).collect())
+            .collect();
+        hungarian(&matrix).into_iter().enumerate().collect()
+    } else {
+        let matrix: Vec<Vec<i64>> = (0..ants)
+            .map(|a| (0..foods).map(|f| cost(f, a)).collect())
+            .collect();
+        hungarian(&matrix)
+            .into_iter()
+            .enumerate()
+            .map(|(ant, food)| (food, ant))
+            .collect()
+    };
+    pairs.retain(|(food, ant)| distances[*food][*ant].is_some());
+    pairs.sort_by_key(|(food, ant)| (distances[*food][*ant], *food, *ant));
+    pairs
+}
+
+/// Column for each row of the cost matrix, with distinct columns and
+/// minimal total cost. Needs at most as many rows as columns.
+fn hungarian(cost: &[Vec<i64>]) -> Vec<usize> {
+    let rows = cost.len();
+    let cols = cost[0].len();
+    // Potentials and matching, one-based with 0 as a virtual column
+    let mut u = vec![0i64; rows + 1];
+    let mut v = vec![0i64; cols + 1];
+    let mut row_of = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+
+    for row in 1..=rows {
+        row_of[0] = row;
+        let mut col = 0;
+        let mut min_v = vec![i64::MAX; cols + 1];
+        let mut used = vec![false; cols + 1];
+        loop {
+            used[col] = true;
+            let current_row = row_of[col];
+            let mut delta = i64::MAX;
+            let mut next_col = 0;
+            for j in 1..=cols {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost[current_row - 1][j - 1] - u[current_row] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = col;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    next_col = j;
+                }
+            }
+            for j in 0..=cols {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            col = next_col;
+            if row_of[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let previous = way[col];
+            row_of[col] = row_of[previous];
+            col = previous;
+        }
+    }
+
+    let mut result = vec![0; rows];
+    for (col, row) in row_of.iter().enumerate().skip(1) {
+        if *row != 0 {
+            result[row - 1] = col - 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::super::scenario::{order, Scenario};
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn optimal_beats_greedy() {
+        let s = Scenario::with_params("..*..a*.a.....", |p| p.spawnradius2 = 0);
+
+        let greedy = food_assignment(&s.world, &s.memory(), &s.params, AssignmentMode::Greedy);
+        let optimal = food_assignment(&s.world, &s.memory(), &s.params, AssignmentMode::Optimal);
+
+        assert_eq!(vec![order("o 0 5 E"), order("o 0 8 W")], greedy);
+        assert_eq!(vec![order("o 0 8 W"), order("o 0 5 W")], optimal);
+        let total = |mode| -> u32 {
+            assign_food(&s.world, &s.memory(), &s.params, mode)
+                .iter()
+                .map(|a| a.distance)
+                .sum()
+        };
+        assert_eq!(7, total(AssignmentMode::Greedy));
+        assert_eq!(5, total(AssignmentMode::Optimal));
+    }
+
+    #[test]
+    fn gathered_within_spawn_radius() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                a*.....
+                .......
+                .......
+                ....*..
+                ....%.a
+                "
+            ),
+            |p| p.spawnradius2 = 1,
+        );
+
+        for mode in [AssignmentMode::Greedy, AssignmentMode::Optimal].iter() {
+            let assignments = assign_food(&s.world, &s.memory(), &s.params, *mode);
+            assert_eq!(
+                vec![
+                    Assignment {
+                        food: pos(0, 1),
+                        ant: pos(0, 0),
+                        target: pos(0, 0),
+                        distance: 0
+                    },
+                    Assignment {
+                        food: pos(3, 4),
+                        ant: pos(4, 6),
+                        target: pos(3, 5),
+                        distance: 2
+                    }
+                ],
+                assignments
+            );
+            assert_eq!(
+                vec![pos(0, 0).pause(), order("o 4 6 N")],
+                food_assignment(&s.world, &s.memory(), &s.params, *mode)
+            );
+        }
+    }
+
+    #[test]
+    fn unreachable_food_and_spare_ants() {
+        let s = Scenario::with_params("a.%*%a*.", |p| p.spawnradius2 = 0);
+
+        for mode in [AssignmentMode::Greedy, AssignmentMode::Optimal].iter() {
+            assert_eq!(
+                vec![order("o 0 5 E")],
+                food_assignment(&s.world, &s.memory(), &s.params, *mode)
+            );
+        }
+    }
+
+    #[test]
+    fn one_ant_for_food_gathered_together() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                ..*.*..
+                .......
+                ...a...
+                ...a...
+                .......
+                "
+            ),
+            |p| p.spawnradius2 = 1,
+        );
+
+        for mode in [AssignmentMode::Greedy, AssignmentMode::Optimal].iter() {
+            assert_eq!(
+                vec![Assignment {
+                    food: pos(0, 2),
+                    ant: pos(2, 3),
+                    target: pos(0, 3),
+                    distance: 2
+                }],
+                assign_food(&s.world, &s.memory(), &s.params, *mode)
+            );
+            assert_eq!(
+                vec![order("o 2 3 N")],
+                food_assignment(&s.world, &s.memory(), &s.params, *mode)
+            );
+        }
+    }
+
+    #[test]
+    fn no_collisions_on_first_steps() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                %%%%%
+                %%a%%
+                %a.*%
+                %%*%%
+                %%%%%
+                "
+            ),
+            |p| p.spawnradius2 = 0,
+        );
+
+        // Both ants can only step onto the same tile
+        let orders = food_assignment(&s.world, &s.memory(), &s.params, AssignmentMode::Optimal);
+
+        assert_eq!(1, orders.len());
+    }
+
+    #[test]
+    fn hungarian_rectangular() {
+        let cost = vec![vec![4, 1, 3, 9], vec![2, 0, 5, 9], vec![3, 2, 2, 9]];
+
+        assert_eq!(vec![1, 0, 2], hungarian(&cost));
+    }
+
+    #[test]
+    fn walks_around_remembered_water() {
+        // Water was only sent in an earlier turn
+        let earlier = Scenario::with_params("a%*...", |p| p.spawnradius2 = 0);
+        let s = Scenario::with_params("a.*...", |p| p.spawnradius2 = 0);
+
+        let orders = food_assignment(
+            &s.world,
+            &earlier.memory(),
+            &s.params,
+            AssignmentMode::Optimal,
+        );
+
+        assert_eq!(vec![order("o 0 0 W")], orders);
+        assert_eq!(
+            4,
+            assign_food(
+                &s.world,
+                &earlier.memory(),
+                &s.params,
+                AssignmentMode::Optimal
+            )[0]
+            .distance
+        );
+    }
+}
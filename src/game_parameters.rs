@@ -1,4 +1,4 @@
-#[derive(PartialEq, Eq, Debug, Default, Hash)]
+#[derive(PartialEq, Eq, Debug, Default, Hash, Clone)]
 pub struct GameParameters {
     // in milliseconds, time given for bot to start up after it is given "ready" (see below)
     pub loadtime_ms: i64,
@@ -19,3 +19,19 @@ pub struct GameParameters {
     // seed for random number generator, useful for reproducing games
     pub player_seed: i64,
 }
+
+impl GameParameters {
+    /// Parameters as used in the official challenge, except for the map
+    /// size and seed which depend on the game.
+    pub fn official() -> Self {
+        GameParameters {
+            loadtime_ms: 3000,
+            turntime_ms: 500,
+            turns: 1000,
+            viewradius2: 77,
+            attackradius2: 5,
+            spawnradius2: 1,
+            ..GameParameters::default()
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Helpers for defending our hills and razing enemy hills. Distances are
+//! walking distances around water, of the world or seen in earlier turns as
+//! remembered.
+
+use super::map_memory::MapMemory;
+use super::{offsets, GameParameters, Position, WorldState};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::slice;
+
+/// Enemy ants that can walk to one of our hills.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HillThreat {
+    pub hill: Position,
+    /// Enemy ants with their owners and walking distances to the hill,
+    /// closest first.
+    pub enemies: Vec<(Position, u8, u32)>,
+}
+
+impl HillThreat {
+    /// Turns the closest enemy ant needs to reach the hill.
+    pub fn turns(&self) -> Option<u32> {
+        self.enemies.first().map(|(_, _, d)| *d)
+    }
+}
+
+/// Tiles at the given walking distance from the hill, row by row.
+pub fn perimeter(
+    world: &WorldState,
+    memory: &MapMemory,
+    hill: &Position,
+    distance: u32,
+) -> Vec<Position> {
+    let map = memory.water_map(world);
+    let distances = map.distances_from(slice::from_ref(hill));
+    map.positions()
+        .filter(|p| distances[map.index(p)] == Some(distance))
+        .collect()
+}
+
+/// Smallest perimeter of the hill within the given walking distance, the
+/// furthest one of those. Every path to the hill from further away passes
+/// one of its tiles, so these are the choke points to hold. Empty if the
+/// hill can not be reached from that far.
+pub fn choke_points(
+    world: &WorldState,
+    memory: &MapMemory,
+    hill: &Position,
+    max_distance: u32,
+) -> Vec<Position> {
+    let map = memory.water_map(world);
+    let distances = map.distances_from(slice::from_ref(hill));
+    let mut best: Option<Vec<Position>> = None;
+    for distance in 1..=max_distance {
+        let ring: Vec<Position> = map
+            .positions()
+            .filter(|p| distances[map.index(p)] == Some(distance))
+            .collect();
+        if best.as_ref().map_or(true, |b| ring.len() <= b.len()) {
+            best = Some(ring);
+        }
+    }
+    best.unwrap_or_default()
+}
+
+/// Visible enemy ants that can walk to each of our hills.
+pub fn hill_threats(world: &WorldState, memory: &MapMemory) -> Vec<HillThreat> {
+    let map = memory.water_map(world);
+    world
+        .hills_for_player(0)
+        .into_iter()
+        .map(|hill| {
+            let distances = map.distances_from(slice::from_ref(&hill));
+            let mut enemies: Vec<(Position, u8, u32)> = world
+                .live_ants
+                .iter()
+                .enumerate()
+                .skip(1)
+                .flat_map(|(owner, ants)| ants.iter().map(move |a| (a, owner as u8)))
+                .filter_map(|(a, owner)| distances[map.index(a)].map(|d| (a.clone(), owner, d)))
+                .collect();
+            enemies.sort_by_key(|(a, owner, d)| (*d, a.clone(), *owner));
+            HillThreat { hill, enemies }
+        })
+        .collect()
+}
+
+/// Positions for up to `defenders` ants guarding the hill at its choke
+/// points within the given walking distance, those closest to the visible
+/// enemy ants first.
+pub fn defensive_formation(
+    world: &WorldState,
+    memory: &MapMemory,
+    hill: &Position,
+    max_distance: u32,
+    defenders: usize,
+) -> Vec<Position> {
+    let map = memory.water_map(world);
+    let enemies: Vec<Position> = world.live_ants.iter().skip(1).flatten().cloned().collect();
+    let from_enemies = map.distances_from(&enemies);
+    let mut chokes = choke_points(world, memory, hill, max_distance);
+    chokes.sort_by_key(|p| (from_enemies[map.index(p)].unwrap_or(u32::MAX), p.clone()));
+    chokes.truncate(defenders);
+    chokes
+}
+
+/// Cheapest walk to the hill, where each step costs 1 plus `threat_cost`
+/// for every enemy ant that has the tile within attack range now. Returns
+/// the tiles after `from` up to and including the hill, or `None` if the
+/// hill can not be reached.
+pub fn attack_path(
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+    from: &Position,
+    hill: &Position,
+    threat_cost: u32,
+) -> Option<Vec<Position>> {
+    let map = memory.water_map(world);
+    let size = map.size.clone();
+    let tile_count = size.row as usize * size.col as usize;
+
+    let mut threat = vec![0u32; tile_count];
+    let range = offsets(params.attackradius2);
+    for enemy in world.live_ants.iter().skip(1).flatten() {
+        for (d_row, d_col) in range.iter() {
+            let p = size.as_size_for_pos(enemy.row as i64 + d_row, enemy.col as i64 + d_col);
+            threat[map.index(&p)] += 1;
+        }
+    }
+
+    let mut cost = vec![u64::MAX; tile_count];
+    let mut previous: Vec<Option<Position>> = vec![None; tile_count];
+    let mut queue = BinaryHeap::new();
+    cost[map.index(from)] = 0;
+    queue.push(Reverse((0u64, from.clone())));
+    while let Some(Reverse((c, p))) = queue.pop() {
+        if p == *hill {
+            let mut path = vec![p];
+            while let Some(before) = &previous[map.index(path.last()?)] {
+                path.push(before.clone());
+            }
+            path.pop();
+            path.reverse();
+            return Some(path);
+        }
+        if c > cost[map.index(&p)] {
+            continue;
+        }
+        for n in p.neighbours(&size) {
+            let i = map.index(&n);
+            let next = c + 1 + threat_cost as u64 * threat[i] as u64;
+            if map.get(&n).is_passable() && next < cost[i] {
+                cost[i] = next;
+                previous[i] = Some(p.clone());
+                queue.push(Reverse((next, n)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::super::scenario::Scenario;
+    use super::*;
+    use indoc::indoc;
+
+    fn walled_room() -> Scenario {
+        Scenario::with_params(
+            indoc!(
+                "
+            b......
+            .%%%%%.
+            .%0..%c
+            .%%%.%.
+            .......
+            "
+            ),
+            |p| p.attackradius2 = 1,
+        )
+    }
+
+    #[test]
+    fn perimeter_and_choke_points() {
+        let s = walled_room();
+        let hill = pos(2, 2);
+
+        assert_eq!(
+            vec![pos(0, 4), pos(4, 3), pos(4, 5)],
+            perimeter(&s.world, &s.memory(), &hill, 5)
+        );
+        assert_eq!(
+            vec![pos(3, 4)],
+            choke_points(&s.world, &s.memory(), &hill, 3)
+        );
+        assert_eq!(
+            vec![pos(2, 4)],
+            defensive_formation(&s.world, &s.memory(), &hill, 2, 3)
+        );
+    }
+
+    #[test]
+    fn enemy_turns_to_reach() {
+        let s = walled_room();
+
+        let threats = hill_threats(&s.world, &s.memory());
+
+        assert_eq!(
+            vec![HillThreat {
+                hill: pos(2, 2),
+                enemies: vec![(pos(0, 0), 1, 8), (pos(2, 6), 2, 8)],
+            }],
+            threats
+        );
+        assert_eq!(Some(8), threats[0].turns());
+    }
+
+    #[test]
+    fn formation_faces_enemies() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+            .......
+            .......
+            .......
+            ...0.b.
+            .......
+            .......
+            .......
+            "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+        let hill = pos(3, 3);
+
+        assert_eq!(
+            vec![pos(3, 4)],
+            defensive_formation(&s.world, &s.memory(), &hill, 1, 1)
+        );
+        assert_eq!(
+            vec![pos(3, 4), pos(2, 3), pos(3, 2), pos(4, 3)],
+            defensive_formation(&s.world, &s.memory(), &hill, 1, 10)
+        );
+    }
+
+    #[test]
+    fn attack_path_avoids_threat() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+            %%%%%%%%%%%
+            %.........%
+            %.........%
+            %a...b...1%
+            %.........%
+            %.........%
+            %%%%%%%%%%%
+            "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+        let (from, hill) = (pos(3, 1), pos(3, 9));
+
+        let direct = attack_path(&s.world, &s.memory(), &s.params, &from, &hill, 0).unwrap();
+        assert_eq!(8, direct.len());
+
+        let path = attack_path(&s.world, &s.memory(), &s.params, &from, &hill, 5).unwrap();
+        assert_eq!(12, path.len());
+        assert_eq!(Some(&hill), path.last());
+        let size = pos(7, 11);
+        let mut at = from;
+        for p in path.iter() {
+            assert_eq!(1, at.distance2(p, &size));
+            assert!(p.distance2(&pos(3, 5), &size) > 1);
+            at = p.clone();
+        }
+
+        assert_eq!(
+            Some(vec![]),
+            attack_path(&s.world, &s.memory(), &s.params, &hill, &hill, 5)
+        );
+        let enclosed = Scenario::with_params("%1%a", |p| p.attackradius2 = 1);
+        assert_eq!(
+            None,
+            attack_path(
+                &enclosed.world,
+                &enclosed.memory(),
+                &enclosed.params,
+                &pos(0, 3),
+                &pos(0, 1),
+                5
+            )
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Influence maps: scalar fields over the wrapped map, e.g. for how many
+//! enemies can attack a tile next turn or how long ago a tile was seen.
+
+use super::map_memory::MapMemory;
+use super::{offsets, pos, GameParameters, Position, WorldState};
+
+/// How fields spread and fade.
+#[derive(PartialEq, Debug, Clone)]
+pub struct InfluenceParameters {
+    /// Factor kept of the value of having seen a tile, per turn.
+    pub decay: f64,
+    /// Part of its value a tile shares with its neighbours per diffusion
+    /// step, 0 to 1.
+    pub diffusion: f64,
+    /// Number of diffusion steps.
+    pub steps: u32,
+}
+
+impl Default for InfluenceParameters {
+    fn default() -> Self {
+        InfluenceParameters {
+            decay: 0.9,
+            diffusion: 0.5,
+            steps: 4,
+        }
+    }
+}
+
+/// Value for every tile of a map, stored row by row.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Field {
+    pub size: Position,
+    values: Vec<f64>,
+}
+
+impl Field {
+    /// Field of given size with all values zero.
+    pub fn new(size: Position) -> Self {
+        let tile_count = size.row as usize * size.col as usize;
+        Field {
+            size,
+            values: vec![0.0; tile_count],
+        }
+    }
+
+    pub fn get(&self, p: &Position) -> f64 {
+        self.values[self.index(p)]
+    }
+
+    pub fn set(&mut self, p: &Position, value: f64) {
+        let index = self.index(p);
+        self.values[index] = value;
+    }
+
+    pub fn add(&mut self, p: &Position, value: f64) {
+        let index = self.index(p);
+        self.values[index] += value;
+    }
+
+    /// Values in row major order.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Positions with their values, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (Position, f64)> + '_ {
+        let cols = self.size.col as usize;
+        self.values
+            .iter()
+            .enumerate()
+            .map(move |(i, v)| (pos((i / cols) as u16, (i % cols) as u16), *v))
+    }
+
+    /// Spread values to neighbouring tiles. In each step every tile hands
+    /// the `diffusion` part of its value in equal shares to its neighbours
+    /// that are not water, so the total is kept. Water stays zero.
+    pub fn diffuse(&self, water: &[Position], params: &InfluenceParameters) -> Field {
+        let size = &self.size;
+        let mut is_water = vec![false; self.values.len()];
+        for w in water {
+            is_water[self.index(w)] = true;
+        }
+        let neighbours: Vec<Vec<usize>> = self
+            .iter()
+            .map(|(p, _)| {
+                p.neighbours(size)
+                    .iter()
+                    .map(|n| self.index(n))
+                    .filter(|n| !is_water[*n])
+                    .collect()
+            })
+            .collect();
+
+        let mut current = self.values.clone();
+        for (i, v) in current.iter_mut().enumerate() {
+            if is_water[i] {
+                *v = 0.0;
+            }
+        }
+        for _ in 0..params.steps {
+            let mut next = current.clone();
+            for (i, v) in current.iter().enumerate() {
+                if is_water[i] || neighbours[i].is_empty() {
+                    continue;
+                }
+                let share = v * params.diffusion / neighbours[i].len() as f64;
+                next[i] -= v * params.diffusion;
+                for n in neighbours[i].iter() {
+                    next[*n] += share;
+                }
+            }
+            current = next;
+        }
+        Field {
+            size: self.size.clone(),
+            values: current,
+        }
+    }
+
+    fn index(&self, p: &Position) -> usize {
+        p.row as usize * self.size.col as usize + p.col as usize
+    }
+}
+
+/// Number of enemy ants that can have each tile within attack range after
+/// their next move, or staying. Ants can not move onto water, of the world
+/// or seen in earlier turns as remembered.
+pub fn threat(world: &WorldState, memory: &MapMemory, params: &GameParameters) -> Field {
+    let enemies: Vec<&Position> = world.live_ants.iter().skip(1).flatten().collect();
+    reach(&enemies, world, memory, params)
+}
+
+/// Number of our ants that can have each tile within attack range after
+/// their next move, or staying, around water as for `threat`.
+pub fn support(world: &WorldState, memory: &MapMemory, params: &GameParameters) -> Field {
+    let own: Vec<&Position> = world.live_ants.iter().take(1).flatten().collect();
+    reach(&own, world, memory, params)
+}
+
+/// Value of seeing each tile again in the given turn, from 0 for tiles
+/// seen in that turn towards 1 the longer ago they were seen, by the
+/// `decay` per turn. Tiles never seen have value 1.
+pub fn exploration<F>(
+    size: Position,
+    turn: u32,
+    last_seen: F,
+    params: &InfluenceParameters,
+) -> Field
+where
+    F: Fn(&Position) -> Option<u32>,
+{
+    let mut field = Field::new(size);
+    let positions: Vec<Position> = field.iter().map(|(p, _)| p).collect();
+    for p in positions {
+        let value = match last_seen(&p) {
+            Some(seen) => 1.0 - params.decay.powi(turn.saturating_sub(seen) as i32),
+            None => 1.0,
+        };
+        field.set(&p, value);
+    }
+    field
+}
+
+fn reach(
+    ants: &[&Position],
+    world: &WorldState,
+    memory: &MapMemory,
+    params: &GameParameters,
+) -> Field {
+    let size = pos(params.rows as u16, params.cols as u16);
+    let mut field = Field::new(size.clone());
+    let mut is_water = vec![false; field.values.len()];
+    for w in world.waters.iter().chain(memory.waters().iter()) {
+        is_water[field.index(w)] = true;
+    }
+    let range = offsets(params.attackradius2);
+
+    // Tiles counted for the current ant, to count each ant once per tile
+    let mut counted = vec![usize::MAX; field.values.len()];
+    for (n, ant) in ants.iter().enumerate() {
+        let moves: Vec<Position> = std::iter::once((*ant).clone())
+            .chain(ant.neighbours(&size))
+            .filter(|p| !is_water[field.index(p)])
+            .collect();
+        for p in moves {
+            for (d_row, d_col) in range.iter() {
+                let target = size.as_size_for_pos(p.row as i64 + d_row, p.col as i64 + d_col);
+                let index = field.index(&target);
+                if counted[index] != n {
+                    counted[index] = n;
+                    field.values[index] += 1.0;
+                }
+            }
+        }
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::scenario::Scenario;
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn threat_after_one_move() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                .......
+                .......
+                ...b...
+                .......
+                a......
+                "
+            ),
+            |p| p.attackradius2 = 1,
+        );
+
+        let threat = threat(&s.world, &s.memory(), &s.params);
+
+        // Attack range of 1 after one step reaches two tiles away
+        assert_eq!(1.0, threat.get(&pos(2, 3)));
+        assert_eq!(1.0, threat.get(&pos(0, 3)));
+        assert_eq!(1.0, threat.get(&pos(3, 4)));
+        assert_eq!(0.0, threat.get(&pos(1, 1)));
+        assert_eq!(1.0, threat.get(&pos(4, 3)));
+        assert_eq!(13, threat.values().iter().filter(|v| **v > 0.0).count());
+        assert_eq!(
+            0.0,
+            support(&s.world, &s.memory(), &s.params).get(&pos(2, 3))
+        );
+    }
+
+    #[test]
+    fn threat_counts_ants_and_wraps() {
+        let s = Scenario::with_params(
+            indoc!(
+                "
+                b%...
+                %....
+                ....c
+                "
+            ),
+            |p| p.attackradius2 = 0,
+        );
+
+        let threat = threat(&s.world, &s.memory(), &s.params);
+
+        // Water blocks two moves of b, both reach around the edges
+        assert_eq!(1.0, threat.get(&pos(0, 0)));
+        assert_eq!(2.0, threat.get(&pos(2, 0)));
+        assert_eq!(2.0, threat.get(&pos(0, 4)));
+        assert_eq!(0.0, threat.get(&pos(1, 0)));
+        assert_eq!(8.0, threat.values().iter().sum::<f64>());
+    }
+
+    #[test]
+    fn support_from_own_ants() {
+        let s = Scenario::with_params("a.a..", |p| p.attackradius2 = 0);
+
+        let support = support(&s.world, &s.memory(), &s.params);
+
+        assert_eq!(vec![1.0, 2.0, 1.0, 1.0, 1.0], support.values());
+    }
+
+    #[test]
+    fn exploration_decays_from_last_seen() {
+        let params = InfluenceParameters {
+            decay: 0.5,
+            ..InfluenceParameters::default()
+        };
+        let last_seen = |p: &Position| match p.col {
+            0 => Some(10),
+            1 => Some(8),
+            _ => None,
+        };
+
+        let field = exploration(pos(1, 3), 10, last_seen, &params);
+
+        assert_eq!(vec![0.0, 0.75, 1.0], field.values());
+    }
+
+    #[test]
+    fn diffusion_keeps_total_and_avoids_water() {
+        let mut field = Field::new(pos(3, 5));
+        field.set(&pos(1, 0), 8.0);
+        field.set(&pos(1, 3), 1.0);
+        let params = InfluenceParameters {
+            diffusion: 0.5,
+            steps: 1,
+            ..InfluenceParameters::default()
+        };
+
+        let diffused = field.diffuse(&[pos(1, 3)], &params);
+
+        // Row wraps around, so (1, 4) is a neighbour of (1, 0)
+        assert_eq!(4.0, diffused.get(&pos(1, 0)));
+        for p in [pos(0, 0), pos(2, 0), pos(1, 1), pos(1, 4)].iter() {
+            assert_eq!(1.0, diffused.get(p));
+        }
+        assert_eq!(8.0, diffused.values().iter().sum::<f64>());
+
+        let diffused = field.diffuse(
+            &[pos(1, 3)],
+            &InfluenceParameters {
+                steps: 10,
+                ..params
+            },
+        );
+        assert!((diffused.values().iter().sum::<f64>() - 8.0).abs() < 1e-9);
+        assert_eq!(0.0, diffused.get(&pos(1, 3)));
+        assert!(diffused.get(&pos(1, 2)) > 0.0);
+    }
+}
@@ -1,12 +1,45 @@
+pub mod ant_tracker;
+pub mod battle;
+pub mod benchmark;
+pub mod bit_grid;
+pub mod debug;
+pub mod engine;
+pub mod exploration;
+pub mod food_assignment;
 pub mod game_parameters;
+pub mod hills;
+pub mod influence;
+pub mod map;
+pub mod map_memory;
+pub mod map_validation;
+pub mod mapgen;
 pub mod position;
+pub mod process_bot;
+pub mod rating;
+pub mod replay;
+mod rng;
+pub mod scenario;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+pub mod tcp_client;
+pub mod tcp_server;
+pub mod tile_index;
+pub mod tournament;
+pub mod world_diff;
 pub mod world_state;
 
+pub use self::debug::DebugSink;
 pub use self::game_parameters::GameParameters;
+pub use self::map::{Map, Tile};
 pub use self::position::Direction::*;
 pub use self::position::*;
+pub use self::process_bot::ProcessBot;
+pub use self::replay::Replay;
 pub use self::world_state::WorldState;
 
+use std::io::BufRead;
+use std::mem;
+
 #[derive(PartialEq, Eq, Debug, Default, Hash, Clone)]
 pub struct Score {
     pub per_player: Vec<u64>,
@@ -14,7 +47,22 @@ pub struct Score {
 
 pub trait Agent {
     fn prepare(&mut self, params: GameParameters);
-    fn make_turn(&mut self, world: WorldState, turn_count: u32) -> Orders;
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders;
+
+    /// Same as `make_turn`, but with a sink for visual debug annotations.
+    /// The sink is a no-op unless the game is recorded into a replay.
+    fn make_turn_with_debug(
+        &mut self,
+        world: &WorldState,
+        turn_count: u32,
+        _debug: &mut DebugSink,
+    ) -> Orders {
+        self.make_turn(world, turn_count)
+    }
+
+    /// Called once when the game is over, with the final world state and
+    /// score.
+    fn at_end(&mut self, _world: WorldState, _score: Score) {}
 }
 
 // TODO Add examples and documentation, e.g.
@@ -25,131 +73,266 @@ pub trait Agent {
 // e.g. Nom (https://github.com/Geal/nom)
 // or Combine (https://github.com/Marwes/combine)
 
-fn parse_turn_0_lines<I>(lines_iter: &mut I) -> GameParameters
+/// Agent wrapper that reports orders without direction, for finding ants
+/// that are paused by mistake. Without it such orders are deliberate holds.
+/// Reported holds are kept with their turn, and are labelled in the debug
+/// annotations when the game is recorded. The orders are passed on as they
+/// are.
+pub struct StrictHolds<A: Agent> {
+    pub agent: A,
+    holds: Vec<(u32, Position)>,
+}
+
+impl<A: Agent> StrictHolds<A> {
+    pub fn new(agent: A) -> Self {
+        StrictHolds {
+            agent,
+            holds: vec![],
+        }
+    }
+
+    /// Turns and positions of all holds so far.
+    pub fn holds(&self) -> &[(u32, Position)] {
+        &self.holds
+    }
+
+    fn check(&mut self, orders: &[Order], turn_count: u32, debug: &mut DebugSink) {
+        for hold in orders.iter().filter(|o| o.is_hold()) {
+            debug.label(hold.pos.clone(), "order without direction");
+            self.holds.push((turn_count, hold.pos.clone()));
+        }
+    }
+}
+
+impl<A: Agent> Agent for StrictHolds<A> {
+    fn prepare(&mut self, params: GameParameters) {
+        self.agent.prepare(params)
+    }
+
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders {
+        let orders = self.agent.make_turn(world, turn_count);
+        self.check(&orders, turn_count, &mut DebugSink::disabled());
+        orders
+    }
+
+    fn make_turn_with_debug(
+        &mut self,
+        world: &WorldState,
+        turn_count: u32,
+        debug: &mut DebugSink,
+    ) -> Orders {
+        let orders = self.agent.make_turn_with_debug(world, turn_count, debug);
+        self.check(&orders, turn_count, debug);
+        orders
+    }
+
+    fn at_end(&mut self, world: WorldState, score: Score) {
+        self.agent.at_end(world, score)
+    }
+}
+
+/// Malformed input from the game server.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ProtocolError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+fn protocol_error(message: String) -> ProtocolError {
+    ProtocolError { message }
+}
+
+/// Parse game parameters as sent after `turn 0`, up to and including
+/// `ready`.
+///
+/// ```
+/// use ants_ai_challenge_api::parse_turn_0;
+///
+/// let params = parse_turn_0("rows 20\r\n  cols 30 \n\nready\n").unwrap();
+/// assert_eq!((20, 30), (params.rows, params.cols));
+/// assert!(parse_turn_0("rows -\nready").is_err());
+/// ```
+pub fn parse_turn_0(text: &str) -> Result<GameParameters, ProtocolError> {
+    parse_turn_0_lines(&mut text.lines().map(String::from))
+}
+
+/// Parse world state as sent after `turn N`, up to and including `go`.
+pub fn parse_turn_x(text: &str) -> Result<WorldState, ProtocolError> {
+    parse_turn_x_lines(&mut text.lines().map(String::from))
+}
+
+/// Like `parse_turn_x`, but fills the given world state, cleared first,
+/// reusing its allocations. On error the state is left empty.
+pub fn parse_turn_x_into(text: &str, world: &mut WorldState) -> Result<(), ProtocolError> {
+    parse_turn_x_lines_into(&mut text.lines().map(String::from), world)
+}
+
+/// Parse final world state and score as sent after `end`, up to and
+/// including `go`.
+pub fn parse_end(text: &str) -> Result<(WorldState, Score), ProtocolError> {
+    parse_end_lines(&mut text.lines().map(String::from))
+}
+
+/// Next line that is not blank.
+fn next_line<I>(lines_iter: &mut I, expected: &str) -> Result<String, ProtocolError>
 where
     I: Iterator<Item = String>,
 {
-    let seed = GameParameters::default();
     lines_iter
-        .take_while(|line| line.trim() != "ready")
-        .fold(seed, |mut params, line| {
-            let mut tokens = line.split_whitespace();
-            let name = tokens.next().expect("Game parameter name");
-            let value: i64 = tokens
-                .next()
-                .expect("Game parameter value")
-                .parse()
-                .expect("Value should be valud integer");
-
-            match name {
-                "loadtime" => params.loadtime_ms = value,
-                "turntime" => params.turntime_ms = value,
-                "rows" => params.rows = value,
-                "cols" => params.cols = value,
-                "turns" => params.turns = value,
-                "viewradius2" => params.viewradius2 = value,
-                "attackradius2" => params.attackradius2 = value,
-                "spawnradius2" => params.spawnradius2 = value,
-                "player_seed" => params.player_seed = value,
-                _ => panic!("Unknown game parameter '{}', with value '{}'.", name, value),
-            };
-            params
-        })
-}
-
-fn parse_turn_x_lines<I>(lines_iter: &mut I) -> WorldState
+        .find(|line| !line.trim().is_empty())
+        .ok_or_else(|| protocol_error(format!("Input ended, expected {}", expected)))
+}
+
+fn parse_turn_0_lines<I>(lines_iter: &mut I) -> Result<GameParameters, ProtocolError>
 where
     I: Iterator<Item = String>,
 {
-    fn parse_row(row: &str) -> u16 {
-        row.parse().expect("Parsing position row")
-    }
-    fn parse_col(col: &str) -> u16 {
-        col.parse().expect("Parsing position column")
+    let mut params = GameParameters::default();
+    loop {
+        let line = next_line(lines_iter, "game parameter or 'ready'")?;
+        let mut tokens = line.split_whitespace();
+        let name = match tokens.next() {
+            Some("ready") => return Ok(params),
+            name => name.unwrap_or_default(),
+        };
+        let value: i64 = match (tokens.next().map(str::parse), tokens.next()) {
+            (Some(Ok(value)), None) => value,
+            _ => return Err(protocol_error(format!("Bad game parameter {:?}", line))),
+        };
+
+        match name {
+            "loadtime" => params.loadtime_ms = value,
+            "turntime" => params.turntime_ms = value,
+            "rows" => params.rows = value,
+            "cols" => params.cols = value,
+            "turns" => params.turns = value,
+            "viewradius2" => params.viewradius2 = value,
+            "attackradius2" => params.attackradius2 = value,
+            "spawnradius2" => params.spawnradius2 = value,
+            "player_seed" => params.player_seed = value,
+            // Parameters of later protocol versions
+            _ => (),
+        };
     }
-    fn parse_owner(owner: &str) -> u8 {
-        owner.parse().expect("Parsing owner")
+}
+
+fn parse_turn_x_lines<I>(lines_iter: &mut I) -> Result<WorldState, ProtocolError>
+where
+    I: Iterator<Item = String>,
+{
+    let mut world = WorldState::default();
+    parse_turn_x_lines_into(lines_iter, &mut world)?;
+    Ok(world)
+}
+
+fn parse_turn_x_lines_into<I>(
+    lines_iter: &mut I,
+    world: &mut WorldState,
+) -> Result<(), ProtocolError>
+where
+    I: Iterator<Item = String>,
+{
+    world.clear();
+    let result = fill_turn_x_lines(lines_iter, world);
+    if result.is_err() {
+        world.clear();
     }
+    result
+}
 
-    lines_iter
-        .take_while(|line| line.trim() != "go")
-        .fold(WorldState::default(), |world, line| {
-            let mut tokens = line.split_whitespace();
-            let item = tokens.next();
-            let row = tokens.next().map(parse_row);
-            let col = tokens.next().map(parse_col);
-            let owner = tokens.next().map(parse_owner);
-
-            let pos = match (row, col) {
-                (Some(r), Some(c)) => pos(r, c),
-                (r, c) => panic!("Bad position when parsing turn X: {:?} {:?}", r, c),
-            };
-
-            match (item, owner) {
-                (Some("w"), None) => world.water(pos),
-                (Some("f"), None) => world.food(pos),
-                (Some("h"), Some(o)) => world.hill(pos, o),
-                (Some("a"), Some(o)) => world.live_ant(pos, o),
-                (Some("d"), Some(o)) => world.dead_ant(pos, o),
-                (i, o) => panic!("Bad item {:?} or owner {:?} when parsing turn X", i, o),
+/// Add the lines up to `go` to the world state.
+fn fill_turn_x_lines<I>(lines_iter: &mut I, world: &mut WorldState) -> Result<(), ProtocolError>
+where
+    I: Iterator<Item = String>,
+{
+    loop {
+        let line = next_line(lines_iter, "world state or 'go'")?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let bad_line = || protocol_error(format!("Bad world state line {:?}", line));
+        let position = |row: &str, col: &str| match (row.parse(), col.parse()) {
+            (Ok(row), Ok(col)) => Ok(pos(row, col)),
+            _ => Err(bad_line()),
+        };
+
+        let taken = mem::take(world);
+        *world = match tokens.as_slice() {
+            ["go"] => {
+                *world = taken;
+                return Ok(());
+            }
+            ["w", row, col] => taken.water(position(row, col)?),
+            ["f", row, col] => taken.food(position(row, col)?),
+            [item, row, col, owner] => {
+                let pos = position(row, col)?;
+                let owner: u8 = owner.parse().map_err(|_| bad_line())?;
+                match *item {
+                    "h" => taken.hill(pos, owner),
+                    "a" => taken.live_ant(pos, owner),
+                    "d" => taken.dead_ant(pos, owner),
+                    _ => return Err(bad_line()),
+                }
             }
-        })
+            _ => return Err(bad_line()),
+        };
+    }
 }
 
-fn parse_end_lines<I>(lines_iter: &mut I) -> (WorldState, Score)
+fn parse_end_lines<I>(lines_iter: &mut I) -> Result<(WorldState, Score), ProtocolError>
 where
     I: Iterator<Item = String>,
 {
-    let mut score = Score::default();
-
-    // players line
-    let players_line = lines_iter.next().expect("Players line");
-    let mut player_tokens = players_line.split_whitespace();
-    let players: u8 = match (player_tokens.next(), player_tokens.next()) {
-        (Some("players"), Some(p)) => p.parse().expect("Number of players"),
-        (a, b) => panic!("Expected 'players <N>', got {:?} {:?}", a, b),
-    };
+    let players_line = next_line(lines_iter, "'players N'")?;
+    let players: usize = match players_line
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["players", p] => p.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| protocol_error(format!("Expected 'players N', got {:?}", players_line)))?;
 
-    // Score line
-    let score_line = lines_iter.next().expect("Score line");
+    let score_line = next_line(lines_iter, "'score ...'")?;
     let mut score_tokens = score_line.split_whitespace();
-    match score_tokens.next() {
-        Some("score") => (),
-        a => panic!("Keyword 'score' should be first on score line, got {:?}", a),
-    };
-    for player_score in score_tokens {
-        score
-            .per_player
-            .push(player_score.parse().expect("Parsable player score"))
+    let per_player = match score_tokens.next() {
+        Some("score") => score_tokens
+            .map(str::parse)
+            .collect::<Result<Vec<u64>, _>>()
+            .ok(),
+        _ => None,
     }
-
-    // parse score safety check
-    if players as usize != score.per_player.len() {
-        panic!(
-            "expected {} players, but only got {} scores in {:?}",
-            players,
-            score.per_player.len(),
-            score
-        )
+    .ok_or_else(|| protocol_error(format!("Expected 'score ...', got {:?}", score_line)))?;
+    if per_player.len() != players {
+        return Err(protocol_error(format!(
+            "Expected {} scores, got {:?}",
+            players, score_line
+        )));
     }
 
-    // world state lines
-    let world_state = parse_turn_x_lines(lines_iter);
-
-    (world_state, score)
+    let world_state = parse_turn_x_lines(lines_iter)?;
+    Ok((world_state, Score { per_player }))
 }
 
+/// Orders in protocol format. Holds have no protocol format, and are left
+/// out since an ant without orders stays where it is anyway.
 fn serialize_orders(orders: &[Order]) -> String {
     let mut result = String::from("");
 
-    for order in orders {
+    for order in orders.iter().filter(|o| !o.is_hold()) {
         result.push_str(&format!("o {} {} ", order.pos.row, order.pos.col));
         result.push(match order.dir {
             Direction::North => 'N',
             Direction::South => 'S',
             Direction::West => 'W',
             Direction::East => 'E',
-            d => panic!("There is no serialization for direction {:?}", d),
+            Direction::NoDirection => unreachable!("Holds are filtered out"),
         });
         result.push('\n');
     }
@@ -157,54 +340,209 @@ fn serialize_orders(orders: &[Order]) -> String {
     result
 }
 
-pub fn run_game(agent: &mut dyn Agent) -> (WorldState, Score) {
-    use std::io::prelude::*;
+fn parse_order_line(line: &str) -> Option<Order> {
+    let mut tokens = line.split_whitespace();
+    let row = match (tokens.next(), tokens.next()) {
+        (Some("o"), Some(row)) => row.parse().ok()?,
+        _ => return None,
+    };
+    let col = tokens.next()?.parse().ok()?;
+    let dir = match tokens.next()? {
+        "N" => Direction::North,
+        "S" => Direction::South,
+        "W" => Direction::West,
+        "E" => Direction::East,
+        _ => return None,
+    };
+    Some(pos(row, col).order(dir))
+}
+
+fn serialize_turn_0(params: &GameParameters) -> String {
+    format!(
+        "turn 0\nloadtime {}\nturntime {}\nrows {}\ncols {}\nturns {}\n\
+         viewradius2 {}\nattackradius2 {}\nspawnradius2 {}\nplayer_seed {}\nready\n",
+        params.loadtime_ms,
+        params.turntime_ms,
+        params.rows,
+        params.cols,
+        params.turns,
+        params.viewradius2,
+        params.attackradius2,
+        params.spawnradius2,
+        params.player_seed
+    )
+}
 
-    let std_in = std::io::stdin();
-    let mut lines_in = std_in.lock().lines().map(|line| line.unwrap());
+fn serialize_world_state(world: &WorldState) -> String {
+    let mut result = String::from("");
+
+    for p in world.waters.iter() {
+        result.push_str(&format!("w {} {}\n", p.row, p.col));
+    }
+    for p in world.foods.iter() {
+        result.push_str(&format!("f {} {}\n", p.row, p.col));
+    }
+    let per_player = [
+        ('h', &world.hills),
+        ('a', &world.live_ants),
+        ('d', &world.dead_ants),
+    ];
+    for (item, positions) in per_player.iter() {
+        for (owner, player_positions) in positions.iter().enumerate() {
+            for p in player_positions {
+                result.push_str(&format!("{} {} {} {}\n", item, p.row, p.col, owner));
+            }
+        }
+    }
+    result.push_str("go\n");
+
+    result
+}
+
+fn serialize_turn_x(turn_count: u32, world: &WorldState) -> String {
+    format!("turn {}\n{}", turn_count, serialize_world_state(world))
+}
+
+fn serialize_end(world: &WorldState, score: &Score) -> String {
+    let scores: Vec<String> = score.per_player.iter().map(u64::to_string).collect();
+    format!(
+        "end\nplayers {}\nscore {}\n{}",
+        score.per_player.len(),
+        scores.join(" "),
+        serialize_world_state(world)
+    )
+}
+
+/// Play a game over stdin and stdout. Panics if the input is malformed.
+pub fn run_game(agent: &mut dyn Agent) -> (WorldState, Score) {
     let mut out = |line| print!("{}", line);
-    run_game_with_io(agent, &mut lines_in, &mut out)
+    run_game_with_io(agent, stdin_lines(), &mut out)
 }
 
+/// Play a game over given input lines and output. Panics if the input is
+/// malformed, see `try_run_game_with_io`.
 pub fn run_game_with_io<I, O>(
     agent: &mut dyn Agent,
-    mut lines_iter: I,
+    lines_iter: I,
     outln: &mut O,
 ) -> (WorldState, Score)
 where
     I: Iterator<Item = String>,
-    O: FnMut(String) -> (),
+    O: FnMut(String),
 {
+    try_run_game_with_io(agent, lines_iter, outln).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Play a game like `run_game_with_io`, but return an error on malformed
+/// input instead of panicking.
+///
+/// Blank lines, surrounding whitespace and CRLF line endings are accepted,
+/// as are unknown game parameters.
+pub fn try_run_game_with_io<I, O>(
+    agent: &mut dyn Agent,
+    lines_iter: I,
+    outln: &mut O,
+) -> Result<(WorldState, Score), ProtocolError>
+where
+    I: Iterator<Item = String>,
+    O: FnMut(String),
+{
+    play_game(agent, lines_iter, outln, None)?
+        .ok_or_else(|| protocol_error("Input ended before 'end'".to_string()))
+}
+
+/// Run game like `run_game`, but also record all turns and debug
+/// annotations into a replay.
+pub fn run_game_recorded(agent: &mut dyn Agent) -> (WorldState, Score, Replay) {
+    let mut out = |line| print!("{}", line);
+    run_game_recorded_with_io(agent, stdin_lines(), &mut out)
+}
+
+pub fn run_game_recorded_with_io<I, O>(
+    agent: &mut dyn Agent,
+    lines_iter: I,
+    outln: &mut O,
+) -> (WorldState, Score, Replay)
+where
+    I: Iterator<Item = String>,
+    O: FnMut(String),
+{
+    let mut replay = Replay::default();
+    let (world, score) = play_game(agent, lines_iter, outln, Some(&mut replay))
+        .and_then(|end| end.ok_or_else(|| protocol_error("Input ended before 'end'".to_string())))
+        .unwrap_or_else(|e| panic!("{}", e));
+    (world, score, replay)
+}
+
+/// Lines of stdin, with invalid UTF-8 replaced rather than failing.
+fn stdin_lines() -> impl Iterator<Item = String> {
+    std::io::stdin()
+        .lock()
+        .split(b'\n')
+        .map_while(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).into_owned())
+}
+
+fn play_game<I, O>(
+    agent: &mut dyn Agent,
+    mut lines_iter: I,
+    outln: &mut O,
+    mut replay: Option<&mut Replay>,
+) -> Result<Option<(WorldState, Score)>, ProtocolError>
+where
+    I: Iterator<Item = String>,
+    O: FnMut(String),
+{
+    let mut debug = match replay {
+        Some(_) => DebugSink::recording(),
+        None => DebugSink::disabled(),
+    };
     let mut turn_count: u32 = 0;
+    let mut world = WorldState::default();
     loop {
-        match lines_iter.next().as_ref().map(String::as_ref) {
-            Some("") => (), /* empty line  */
-            Some("turn 0") => {
-                let params = parse_turn_0_lines(&mut lines_iter);
+        let line = match lines_iter.next() {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => (),
+            ["turn", "0"] => {
+                let params = parse_turn_0_lines(&mut lines_iter)?;
+                if let Some(replay) = replay.as_mut() {
+                    replay.params = params.clone();
+                }
                 agent.prepare(params);
                 outln(String::from("go\n"));
             }
-            Some(x) if x.starts_with("turn") => {
+            ["turn", _] => {
                 turn_count += 1;
-                let world = parse_turn_x_lines(&mut lines_iter);
-                let orders = agent.make_turn(world, turn_count);
+                parse_turn_x_lines_into(&mut lines_iter, &mut world)?;
+                let recorded_world = replay.as_ref().map(|_| world.clone());
+                let orders = agent.make_turn_with_debug(&world, turn_count, &mut debug);
                 let output = serialize_orders(&orders);
+                if let (Some(replay), Some(world)) = (replay.as_mut(), recorded_world) {
+                    replay.push_debug_turn(world, orders, debug.take());
+                }
                 outln(output);
                 outln("go\n".to_string());
             }
-            Some("end") => break,
-            Some(x) => panic!("Unexpected input line <{:?}>", x),
-            None => panic!("Unexpected end of input lines"),
+            ["end"] => break,
+            _ => return Err(protocol_error(format!("Unexpected input line {:?}", line))),
         }
     }
 
-    parse_end_lines(&mut lines_iter)
+    let (world, score) = parse_end_lines(&mut lines_iter)?;
+    agent.at_end(world.clone(), score.clone());
+    Ok(Some((world, score)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use indoc::indoc;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
 
     #[test]
     fn parse_turn_0_success() {
@@ -233,7 +571,7 @@ mod tests {
         };
 
         let actual = parse_turn_0_lines(&mut input.lines().map(String::from));
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -257,7 +595,26 @@ mod tests {
             .hill(pos(7, 12), 1);
 
         let actual = parse_turn_x_lines(&mut input.lines().map(String::from));
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
+    }
+
+    #[test]
+    fn parse_turn_into_reuses_world() {
+        let mut world = WorldState::default();
+        parse_turn_x_into("a 1 1 0\na 2 2 1\nd 3 3 2\nf 4 4\ngo", &mut world).unwrap();
+        let capacity = world.live_ants[0].capacity();
+
+        parse_turn_x_into("w 5 5\na 6 6 0\ngo", &mut world).unwrap();
+
+        assert_eq!(
+            parse_turn_x("w 5 5\na 6 6 0\ngo"),
+            Ok(world.clone().trimmed())
+        );
+        assert_eq!(capacity, world.live_ants[0].capacity());
+        assert!(parse_turn_x_into("x 1 2 0\ngo", &mut world).is_err());
+        assert_eq!(WorldState::default(), world.clone().trimmed());
+        assert!(parse_turn_x_into("a 1 1 0\nf 2 2\n", &mut world).is_err());
+        assert_eq!(WorldState::default(), world.clone().trimmed());
     }
 
     #[test]
@@ -283,7 +640,7 @@ mod tests {
         };
 
         let (actual_world_state, actual_score) =
-            parse_end_lines(&mut input.lines().map(String::from));
+            parse_end_lines(&mut input.lines().map(String::from)).unwrap();
 
         assert_eq!(expected_world_state, actual_world_state);
         assert_eq!(expected_score, actual_score);
@@ -291,12 +648,12 @@ mod tests {
 
     #[test]
     fn serialize_orders_success() {
-        let mut orders: Orders = vec![];
-
-        orders.push(pos(10, 8).order(Direction::North));
-        orders.push(pos(2, 3).order(Direction::South));
-        orders.push(pos(4, 5).order(Direction::East));
-        orders.push(pos(6, 7).order(Direction::West));
+        let orders: Orders = vec![
+            pos(10, 8).order(Direction::North),
+            pos(2, 3).order(Direction::South),
+            pos(4, 5).order(Direction::East),
+            pos(6, 7).order(Direction::West),
+        ];
 
         let expected = indoc!(
             "o 10 8 N
@@ -309,4 +666,290 @@ mod tests {
         let actual = serialize_orders(&orders);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn serialize_orders_omits_holds() {
+        let orders = vec![pos(1, 2).pause(), pos(3, 4).east(), pos(5, 6).pause()];
+
+        assert_eq!("o 3 4 E\n", serialize_orders(&orders));
+        assert_eq!("", serialize_orders(&[pos(1, 2).pause()]));
+    }
+
+    /// Holds every ant.
+    struct Holder;
+
+    impl Agent for Holder {
+        fn prepare(&mut self, _params: GameParameters) {}
+
+        fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+            world
+                .live_ants_for_player(0)
+                .iter()
+                .map(|a| a.pause())
+                .collect()
+        }
+    }
+
+    const HOLD_GAME: &str = "turn 0\nrows 3\ncols 3\nready\nturn 1\na 1 1 0\na 2 2 1\ngo\n\
+                             end\nplayers 2\nscore 1 1\ngo\n";
+
+    #[test]
+    fn holds_are_recorded_but_not_sent() {
+        let mut output = String::new();
+        let (_, _, replay) = run_game_recorded_with_io(
+            &mut Holder,
+            HOLD_GAME.lines().map(String::from),
+            &mut |line| output.push_str(&line),
+        );
+
+        assert_eq!("go\ngo\n", output);
+        assert_eq!(vec![pos(1, 1).pause()], replay.turns[0].orders);
+    }
+
+    #[test]
+    fn strict_holds_reports_holds() {
+        let mut strict = StrictHolds::new(Holder);
+        let mut output = String::new();
+        let (_, _, replay) = run_game_recorded_with_io(
+            &mut strict,
+            HOLD_GAME.lines().map(String::from),
+            &mut |line| output.push_str(&line),
+        );
+
+        assert_eq!("go\ngo\n", output);
+        assert_eq!(&[(1, pos(1, 1))], strict.holds());
+        assert_eq!(
+            vec![debug::Annotation::Label {
+                pos: pos(1, 1),
+                text: "order without direction".to_string()
+            }],
+            replay.turns[0].debug
+        );
+    }
+
+    #[test]
+    fn parse_order_line_success() {
+        assert_eq!(Some(pos(1, 2).north()), parse_order_line("o 1 2 N"));
+        assert_eq!(Some(pos(3, 4).west()), parse_order_line("  o 3 4 W  "));
+        assert_eq!(None, parse_order_line("o 1 2 X"));
+        assert_eq!(None, parse_order_line("o 1 N"));
+        assert_eq!(None, parse_order_line("go"));
+    }
+
+    #[test]
+    fn serialize_then_parse_round_trip() {
+        let params = GameParameters {
+            loadtime_ms: 3000,
+            turntime_ms: 1000,
+            rows: 20,
+            cols: 30,
+            turns: 500,
+            viewradius2: 55,
+            attackradius2: 5,
+            spawnradius2: 1,
+            player_seed: 42,
+        };
+        let world = WorldState::default()
+            .water(pos(7, 6))
+            .food(pos(6, 5))
+            .hill(pos(7, 12), 1)
+            .live_ant(pos(10, 8), 0)
+            .live_ant(pos(7, 9), 1)
+            .dead_ant(pos(17, 19), 2);
+        let score = Score {
+            per_player: vec![3, 1, 0],
+        };
+
+        let turn_0 = serialize_turn_0(&params);
+        let mut turn_0 = turn_0.lines().map(String::from);
+        assert_eq!(Some(String::from("turn 0")), turn_0.next());
+        assert_eq!(Ok(params), parse_turn_0_lines(&mut turn_0));
+
+        let turn_x = serialize_turn_x(7, &world);
+        let mut turn_x = turn_x.lines().map(String::from);
+        assert_eq!(Some(String::from("turn 7")), turn_x.next());
+        assert_eq!(Ok(world.clone()), parse_turn_x_lines(&mut turn_x));
+
+        let end = serialize_end(&world, &score);
+        let mut end = end.lines().map(String::from);
+        assert_eq!(Some(String::from("end")), end.next());
+        assert_eq!(Ok((world, score)), parse_end_lines(&mut end));
+    }
+
+    fn test_map() -> Map {
+        Map::parse("rows 4\ncols 6\nplayers 3\nm .0%..1\nm %..2.*\nm ..%%..\nm b.....\n").unwrap()
+    }
+
+    /// Agent recording what it was given, ordering every ant north.
+    #[derive(Default)]
+    struct Recorder {
+        params: GameParameters,
+        turns: Vec<WorldState>,
+        end: Option<(WorldState, Score)>,
+    }
+
+    impl Agent for Recorder {
+        fn prepare(&mut self, params: GameParameters) {
+            self.params = params;
+        }
+
+        fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+            let orders = world
+                .live_ants_for_player(0)
+                .iter()
+                .map(|a| a.order(North))
+                .collect();
+            self.turns.push(world.clone());
+            orders
+        }
+
+        fn at_end(&mut self, world: WorldState, score: Score) {
+            self.end = Some((world, score));
+        }
+    }
+
+    #[test]
+    fn run_game_tolerates_crlf_whitespace_and_blank_lines() {
+        let input = "\r\nturn 0\r\n loadtime 3000 \r\nrows 4\r\ncols 5\r\n\
+                     future_param 7\r\nready \r\n\r\nturn 1\r\n\r\na 1 2 0\t\r\n\
+                     f 0 0\r\ngo\r\nend\r\nplayers 2\r\n\r\nscore  1 0 \r\ngo\r\n";
+        let mut agent = Recorder::default();
+        let mut output = String::new();
+
+        let result =
+            try_run_game_with_io(&mut agent, input.lines().map(String::from), &mut |line| {
+                output.push_str(&line)
+            });
+
+        assert_eq!(
+            Ok((
+                WorldState::default(),
+                Score {
+                    per_player: vec![1, 0]
+                }
+            )),
+            result
+        );
+        assert_eq!(
+            (3000, 4, 5),
+            (
+                agent.params.loadtime_ms,
+                agent.params.rows,
+                agent.params.cols
+            )
+        );
+        assert_eq!(
+            vec![WorldState::default().live_ant(pos(1, 2), 0).food(pos(0, 0))],
+            agent.turns
+        );
+        assert_eq!("go\no 1 2 N\ngo\n", output);
+    }
+
+    /// Inputs that crashed the parsers before they returned errors.
+    #[test]
+    fn parse_errors_instead_of_panics() {
+        assert!(parse_turn_0("rows\nready").is_err());
+        assert!(parse_turn_0("rows 1 2\nready").is_err());
+        assert!(parse_turn_0("rows x\nready").is_err());
+        assert!(parse_turn_0("rows 20\n").is_err());
+        assert_eq!(Ok(-1), parse_turn_0("rows -1\nready").map(|p| p.rows));
+
+        assert!(parse_turn_x("a -1 2 0\ngo").is_err());
+        assert!(parse_turn_x("a 1 2 -3\ngo").is_err());
+        assert!(parse_turn_x("a 1 2 256\ngo").is_err());
+        assert!(parse_turn_x("w 1\ngo").is_err());
+        assert!(parse_turn_x("w 1 2 0\ngo").is_err());
+        assert!(parse_turn_x("x 1 2 0\ngo").is_err());
+        assert!(parse_turn_x("f 70000 1\ngo").is_err());
+        assert!(parse_turn_x("f 1 1\n").is_err());
+
+        assert!(parse_end("players 2\nscore 1\ngo").is_err());
+        assert!(parse_end("players -2\nscore\ngo").is_err());
+        assert!(parse_end("players 1\nscore -1\ngo").is_err());
+        assert!(parse_end("score 1\ngo").is_err());
+        assert!(parse_end("players 1").is_err());
+
+        let mut agent = Recorder::default();
+        for input in ["turnip\n", "turn 1 2\ngo\n", "end\n", "turn 0\nrows 2\n"].iter() {
+            let result =
+                try_run_game_with_io(&mut agent, input.lines().map(String::from), &mut |_| ());
+            assert!(result.is_err(), "{:?} gave {:?}", input, result);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_input_never_panics(
+            lines in vec(prop_oneof![
+                any::<String>(),
+                select(vec![
+                    "turn 0", "turn 1", "ready", "go", "end", "players 2", "score 1 2",
+                    "a 1 2 0", "h 3 4 1", "w -1 3", "f 99999 1", "d 1 1 x", "rows 20", "",
+                    " \r", "o 1 2 N",
+                ])
+                .prop_map(String::from),
+            ], 0..40)
+        ) {
+            let text = lines.join("\n");
+            let _ = parse_turn_0(&text);
+            let _ = parse_turn_x(&text);
+            let _ = parse_end(&text);
+            let _ = try_run_game_with_io(
+                &mut Recorder::default(),
+                lines.into_iter(),
+                &mut |_| (),
+            );
+        }
+
+        #[test]
+        fn game_parameters_round_trip(params in strategies::game_parameters()) {
+            let turn_0 = serialize_turn_0(&params);
+            let mut lines = turn_0.lines().map(String::from).skip(1);
+            prop_assert_eq!(Ok(params), parse_turn_0_lines(&mut lines));
+        }
+
+        #[test]
+        fn world_state_round_trip(world in strategies::world_state(&test_map())) {
+            let text = serialize_world_state(&world);
+            let parsed = parse_turn_x_lines(&mut text.lines().map(String::from)).unwrap();
+            prop_assert_eq!(&world, &parsed);
+            prop_assert_eq!(text, serialize_world_state(&parsed));
+        }
+
+        #[test]
+        fn orders_round_trip(orders in vec(strategies::order(pos(200, 200)), 0..20)) {
+            let text = serialize_orders(&orders);
+            let parsed: Orders = text.lines().filter_map(parse_order_line).collect();
+            prop_assert_eq!(orders, parsed);
+        }
+
+        #[test]
+        fn run_game_with_io_round_trip(transcript in strategies::transcript(&test_map(), 5)) {
+            let mut agent = Recorder::default();
+            let mut output = String::new();
+            let (world, score) = run_game_with_io(
+                &mut agent,
+                transcript.lines().into_iter(),
+                &mut |line| output.push_str(&line),
+            );
+
+            prop_assert_eq!(&transcript.params, &agent.params);
+            let turns: Vec<WorldState> = agent.turns.iter().cloned().map(WorldState::trimmed).collect();
+            prop_assert_eq!(&transcript.turns, &turns);
+            prop_assert_eq!(&transcript.end_world, &world);
+            prop_assert_eq!(&transcript.score, &score);
+            prop_assert_eq!(Some((world, score)), agent.end);
+
+            let expected_output: String = transcript
+                .turns
+                .iter()
+                .map(|w| {
+                    let orders: Orders =
+                        w.live_ants_for_player(0).iter().map(|a| a.order(North)).collect();
+                    serialize_orders(&orders) + "go\n"
+                })
+                .collect();
+            prop_assert_eq!("go\n".to_string() + &expected_output, output);
+        }
+    }
 }
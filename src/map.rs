@@ -0,0 +1,315 @@
+use super::{pos, Position, WorldState};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Content of a single map tile, as written in the official map file format.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Tile {
+    /// `.`
+    Land,
+    /// `%`
+    Water,
+    /// `*`
+    Food,
+    /// `0` to `9`
+    Hill(u8),
+    /// `a` to `j`
+    Ant(u8),
+    /// `A` to `J`
+    AntOnHill(u8),
+    /// `?`
+    Unseen,
+}
+
+impl Tile {
+    pub fn from_char(c: char) -> Option<Tile> {
+        match c {
+            '.' => Some(Tile::Land),
+            '%' => Some(Tile::Water),
+            '*' => Some(Tile::Food),
+            '?' => Some(Tile::Unseen),
+            '0'..='9' => Some(Tile::Hill(c as u8 - b'0')),
+            'a'..='j' => Some(Tile::Ant(c as u8 - b'a')),
+            'A'..='J' => Some(Tile::AntOnHill(c as u8 - b'A')),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::Land => '.',
+            Tile::Water => '%',
+            Tile::Food => '*',
+            Tile::Unseen => '?',
+            Tile::Hill(p) => (b'0' + p) as char,
+            Tile::Ant(p) => (b'a' + p) as char,
+            Tile::AntOnHill(p) => (b'A' + p) as char,
+        }
+    }
+
+    /// Whether ants can ever walk on this tile
+    pub fn is_passable(self) -> bool {
+        !matches!(self, Tile::Water | Tile::Unseen)
+    }
+}
+
+/// Error when parsing a map, with one-based line number.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MapParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MapParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for MapParseError {}
+
+/// Complete map of the world, e.g. as read from a map file.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Map {
+    /// Map size, rows and columns.
+    pub size: Position,
+    /// Number of players, as given in the map header.
+    pub players: u8,
+    tiles: Vec<Tile>,
+}
+
+impl Map {
+    /// Map of given size with only land.
+    pub fn new(size: Position, players: u8) -> Self {
+        let tile_count = size.row as usize * size.col as usize;
+        Map {
+            size,
+            players,
+            tiles: vec![Tile::Land; tile_count],
+        }
+    }
+
+    /// Map of given size with only the given water, e.g. all water seen so
+    /// far, for finding paths.
+    pub fn with_water(size: Position, water: &[Position]) -> Self {
+        let mut map = Map::new(size, 1);
+        for w in water {
+            map.set(w, Tile::Water);
+        }
+        map
+    }
+
+    pub fn get(&self, pos: &Position) -> Tile {
+        self.tiles[self.index(pos)]
+    }
+
+    pub fn set(&mut self, pos: &Position, tile: Tile) {
+        let index = self.index(pos);
+        self.tiles[index] = tile;
+    }
+
+    /// Row major index of position, e.g. into the result of
+    /// `distances_from`.
+    pub fn index(&self, pos: &Position) -> usize {
+        pos.row as usize * self.size.col as usize + pos.col as usize
+    }
+
+    /// All map positions, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let size = self.size.clone();
+        (0..size.row).flat_map(move |row| (0..size.col).map(move |col| pos(row, col)))
+    }
+
+    /// Walking distance from the closest of the given sources to every
+    /// tile, in row major order. Unreachable tiles get `None`.
+    pub fn distances_from(&self, sources: &[Position]) -> Vec<Option<u32>> {
+        let mut distances = vec![None; self.tiles.len()];
+        let mut queue = VecDeque::new();
+        for source in sources {
+            distances[self.index(source)] = Some(0);
+            queue.push_back(source.clone());
+        }
+
+        while let Some(p) = queue.pop_front() {
+            let next = distances[self.index(&p)].map(|d| d + 1);
+            for n in p.neighbours(&self.size) {
+                let i = self.index(&n);
+                if distances[i].is_none() && self.tiles[i].is_passable() {
+                    distances[i] = next;
+                    queue.push_back(n);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Hill positions for each player (zero indexed).
+    pub fn hills(&self) -> Vec<Vec<Position>> {
+        self.to_world_state().hills
+    }
+
+    /// Parse map in the official map file format, e.g.
+    ///
+    /// ```text
+    /// rows 2
+    /// cols 4
+    /// players 2
+    /// m A.%1
+    /// m .*.b
+    /// ```
+    pub fn parse(text: &str) -> Result<Map, MapParseError> {
+        let mut rows: Option<u16> = None;
+        let mut cols: Option<u16> = None;
+        let mut players: Option<u8> = None;
+        let mut tiles: Vec<Tile> = vec![];
+        let mut map_rows: u16 = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| MapParseError {
+                line: index + 1,
+                message,
+            };
+            let mut tokens = line.split_whitespace();
+            let key = tokens.next();
+            let value = tokens.next();
+
+            match key {
+                None => (),
+                Some("rows") => rows = Some(parse_value(value).map_err(error)?),
+                Some("cols") => cols = Some(parse_value(value).map_err(error)?),
+                Some("players") => players = Some(parse_value(value).map_err(error)?),
+                Some("m") => {
+                    let row = value.unwrap_or("");
+                    if Some(row.chars().count()) != cols.map(usize::from) {
+                        return Err(error(format!(
+                            "Map row of length {} does not match cols {:?}",
+                            row.chars().count(),
+                            cols
+                        )));
+                    }
+                    for c in row.chars() {
+                        match Tile::from_char(c) {
+                            Some(tile) => tiles.push(tile),
+                            None => return Err(error(format!("Unknown map character {:?}", c))),
+                        }
+                    }
+                    map_rows += 1;
+                }
+                // Other keys, e.g. comments, are ignored.
+                Some(_) => (),
+            }
+        }
+
+        let last_line = text.lines().count();
+        let error = |message: &str| MapParseError {
+            line: last_line,
+            message: message.to_string(),
+        };
+        let rows = rows.ok_or_else(|| error("Missing 'rows'"))?;
+        let cols = cols.ok_or_else(|| error("Missing 'cols'"))?;
+        let players = players.ok_or_else(|| error("Missing 'players'"))?;
+        if map_rows != rows {
+            return Err(error(&format!(
+                "Got {} map rows, but header says {}",
+                map_rows, rows
+            )));
+        }
+
+        Ok(Map {
+            size: pos(rows, cols),
+            players,
+            tiles,
+        })
+    }
+
+    /// Full information world state of the map.
+    pub fn to_world_state(&self) -> WorldState {
+        self.positions()
+            .fold(WorldState::default(), |world, p| match self.get(&p) {
+                Tile::Land | Tile::Unseen => world,
+                Tile::Water => world.water(p),
+                Tile::Food => world.food(p),
+                Tile::Hill(o) => world.hill(p, o),
+                Tile::Ant(o) => world.live_ant(p, o),
+                Tile::AntOnHill(o) => world.hill(p.clone(), o).live_ant(p, o),
+            })
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: Option<&str>) -> Result<T, String> {
+    let value = value.ok_or_else(|| String::from("Missing value"))?;
+    value.parse().map_err(|_| format!("Bad value {:?}", value))
+}
+
+impl fmt::Display for Map {
+    /// Write map in the official map file format.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rows {}", self.size.row)?;
+        writeln!(f, "cols {}", self.size.col)?;
+        writeln!(f, "players {}", self.players)?;
+        for row in self.tiles.chunks(self.size.col.max(1) as usize) {
+            let line: String = row.iter().map(|t| t.to_char()).collect();
+            writeln!(f, "m {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse_and_display_success() {
+        let text = indoc!(
+            "rows 2
+            cols 4
+            players 2
+            m A.%1
+            m .*.b
+            "
+        );
+
+        let map = Map::parse(text).expect("Parsable map");
+
+        assert_eq!(pos(2, 4), map.size);
+        assert_eq!(2, map.players);
+        assert_eq!(Tile::AntOnHill(0), map.get(&pos(0, 0)));
+        assert_eq!(Tile::Water, map.get(&pos(0, 2)));
+        assert_eq!(Tile::Hill(1), map.get(&pos(0, 3)));
+        assert_eq!(Tile::Food, map.get(&pos(1, 1)));
+        assert_eq!(Tile::Ant(1), map.get(&pos(1, 3)));
+        assert_eq!(text, map.to_string());
+    }
+
+    #[test]
+    fn parse_failure() {
+        let bad_row = "rows 1\ncols 3\nplayers 1\nm ..\n";
+        assert_eq!(4, Map::parse(bad_row).unwrap_err().line);
+
+        let bad_char = "rows 1\ncols 3\nplayers 1\nm .x.\n";
+        assert_eq!(4, Map::parse(bad_char).unwrap_err().line);
+
+        let wrapping_row = format!("rows 1\ncols 3\nplayers 1\nm {}\n", ".".repeat(65539));
+        assert_eq!(4, Map::parse(&wrapping_row).unwrap_err().line);
+
+        let missing_rows = "rows 2\ncols 3\nplayers 1\nm ...\n";
+        assert!(Map::parse(missing_rows).is_err());
+    }
+
+    #[test]
+    fn to_world_state_success() {
+        let map = Map::parse("rows 2\ncols 4\nplayers 2\nm A.%1\nm .*.b\n").unwrap();
+
+        let expected = WorldState::default()
+            .hill(pos(0, 0), 0)
+            .live_ant(pos(0, 0), 0)
+            .water(pos(0, 2))
+            .hill(pos(0, 3), 1)
+            .food(pos(1, 1))
+            .live_ant(pos(1, 3), 1);
+
+        assert_eq!(expected, map.to_world_state());
+    }
+}
@@ -0,0 +1,152 @@
+//! What a bot knows of the map across turns: water is only sent the first
+//! time it is seen, hills only while in sight, and nothing tells when a
+//! tile was last in sight.
+
+use super::map::{Map, Tile};
+use super::{offsets, pos, GameParameters, Position, WorldState};
+use std::collections::BTreeMap;
+
+/// Terrain and hills seen so far and when each tile was last visible to
+/// our ants.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MapMemory {
+    /// Known terrain, `Land` or `Water` once seen, otherwise `Unseen`.
+    map: Map,
+    /// Owner of each hill ever seen, razed or not.
+    hills: BTreeMap<Position, u8>,
+    last_seen: Vec<Option<u32>>,
+    visible: Vec<bool>,
+    view: Vec<(i64, i64)>,
+    turn: u32,
+}
+
+impl MapMemory {
+    pub fn new(params: &GameParameters) -> Self {
+        let size = pos(params.rows as u16, params.cols as u16);
+        let tile_count = size.row as usize * size.col as usize;
+        let mut map = Map::new(size, 1);
+        for p in map.positions().collect::<Vec<_>>() {
+            map.set(&p, Tile::Unseen);
+        }
+        MapMemory {
+            map,
+            hills: BTreeMap::new(),
+            last_seen: vec![None; tile_count],
+            visible: vec![false; tile_count],
+            view: offsets(params.viewradius2),
+            turn: 0,
+        }
+    }
+
+    /// Remember the world of a turn: the water and hills in it, and the
+    /// tiles within view radius of our ants as seen in that turn.
+    pub fn update(&mut self, world: &WorldState, turn: u32) {
+        self.turn = turn;
+        for v in self.visible.iter_mut() {
+            *v = false;
+        }
+        for w in world.waters.iter() {
+            self.map.set(w, Tile::Water);
+        }
+        for (player, hills) in world.hills.iter().enumerate() {
+            for h in hills.iter() {
+                self.hills.insert(h.clone(), player as u8);
+            }
+        }
+        let size = self.map.size.clone();
+        for ant in world.live_ants_for_player(0) {
+            for (d_row, d_col) in self.view.iter() {
+                let p = size.as_size_for_pos(ant.row as i64 + d_row, ant.col as i64 + d_col);
+                let i = self.map.index(&p);
+                self.visible[i] = true;
+                self.last_seen[i] = Some(turn);
+                if self.map.get(&p) == Tile::Unseen {
+                    self.map.set(&p, Tile::Land);
+                }
+            }
+        }
+    }
+
+    /// Known terrain, with `Unseen` for tiles never seen.
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    pub fn size(&self) -> &Position {
+        &self.map.size
+    }
+
+    /// Turn of the last update.
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Whether the tile was visible in the last update.
+    pub fn is_visible(&self, p: &Position) -> bool {
+        self.visible[self.map.index(p)]
+    }
+
+    /// Last turn the tile was visible in, if ever.
+    pub fn last_seen(&self, p: &Position) -> Option<u32> {
+        self.last_seen[self.map.index(p)]
+    }
+
+    pub fn is_water(&self, p: &Position) -> bool {
+        self.map.get(p) == Tile::Water
+    }
+
+    /// All water seen so far, row by row.
+    pub fn waters(&self) -> Vec<Position> {
+        self.map.positions().filter(|p| self.is_water(p)).collect()
+    }
+
+    /// Map of the remembered water and the water of the world, with tiles
+    /// never seen as land, for finding paths.
+    pub fn water_map(&self, world: &WorldState) -> Map {
+        let mut waters = self.waters();
+        waters.extend(world.waters.iter().cloned());
+        Map::with_water(self.size().clone(), &waters)
+    }
+
+    /// Owner of the hill seen on the tile, if any, razed or not.
+    pub fn hill(&self, p: &Position) -> Option<u8> {
+        self.hills.get(p).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembers_water_and_last_seen() {
+        let params = GameParameters {
+            rows: 3,
+            cols: 8,
+            viewradius2: 1,
+            ..GameParameters::official()
+        };
+        let mut memory = MapMemory::new(&params);
+
+        let world = WorldState::default()
+            .live_ant(pos(1, 1), 0)
+            .live_ant(pos(1, 6), 1)
+            .water(pos(1, 2))
+            .hill(pos(1, 1), 0);
+        memory.update(&world, 1);
+        memory.update(&WorldState::default().live_ant(pos(1, 0), 0), 2);
+
+        assert_eq!(Some(2), memory.last_seen(&pos(1, 1)));
+        assert_eq!(Some(1), memory.last_seen(&pos(1, 2)));
+        assert_eq!(Some(2), memory.last_seen(&pos(1, 7)));
+        assert_eq!(None, memory.last_seen(&pos(1, 6)));
+        assert!(memory.is_visible(&pos(1, 7)));
+        assert!(!memory.is_visible(&pos(1, 2)));
+        assert_eq!(vec![pos(1, 2)], memory.waters());
+        assert_eq!(Some(0), memory.hill(&pos(1, 1)));
+        assert_eq!(None, memory.hill(&pos(1, 6)));
+        assert_eq!(Tile::Land, memory.map().get(&pos(0, 1)));
+        assert_eq!(Tile::Unseen, memory.map().get(&pos(0, 2)));
+        assert_eq!(2, memory.turn());
+    }
+}
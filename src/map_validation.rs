@@ -0,0 +1,317 @@
+use super::map::Map;
+use super::Position;
+
+/// Problem making a map broken or unfair.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum MapProblem {
+    /// Player index in a hill or ant is not below the header player count.
+    UnknownPlayer { player: u8, players: u8 },
+    /// Player in the header has no hill.
+    PlayerWithoutHill { player: u8 },
+    /// Players have different numbers of hills or initial ants.
+    UnevenStart { hills: Vec<usize>, ants: Vec<usize> },
+    /// Land region that can not be reached from any hill.
+    UnreachableLand { size: usize, example: Position },
+    /// Hill from which no enemy hill can be reached.
+    IsolatedHill { player: u8, hill: Position },
+    /// The sorted walking distances from each player's hills to the enemy
+    /// hills differ between players.
+    AsymmetricHillDistances { per_player: Vec<Vec<u32>> },
+}
+
+/// Fairness metrics for a single player.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PlayerMetrics {
+    pub player: u8,
+    /// Number of land tiles within walking distance of the player's hills.
+    pub reachable_area: usize,
+    /// Share of land tiles, i.e. tiles where food can appear, that are
+    /// closer to this player's hills than to any other player's. Ties are
+    /// split evenly.
+    pub food_share: f64,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct MapReport {
+    pub problems: Vec<MapProblem>,
+    pub players: Vec<PlayerMetrics>,
+}
+
+impl MapReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check map for problems and compute per player metrics, with
+/// reachable area counted within the given number of steps.
+///
+/// # Example
+/// ```
+/// use ants_ai_challenge_api::map_validation::validate;
+/// use ants_ai_challenge_api::Map;
+///
+/// let map = Map::parse("rows 1\ncols 6\nplayers 2\nm A..B..\n").unwrap();
+/// let report = validate(&map, 2);
+/// assert!(report.is_valid());
+/// assert_eq!(0.5, report.players[0].food_share);
+/// ```
+pub fn validate(map: &Map, steps: u32) -> MapReport {
+    let world = map.to_world_state();
+    let players = map.players;
+    let hills: Vec<Vec<Position>> = (0..players).map(|p| world.hills_for_player(p)).collect();
+    let mut problems = vec![];
+
+    // Header consistency
+    let found_players = std::cmp::max(world.hills.len(), world.live_ants.len());
+    if found_players > players as usize {
+        problems.push(MapProblem::UnknownPlayer {
+            player: found_players as u8 - 1,
+            players,
+        });
+    }
+    for (player, player_hills) in hills.iter().enumerate() {
+        if player_hills.is_empty() {
+            problems.push(MapProblem::PlayerWithoutHill {
+                player: player as u8,
+            });
+        }
+    }
+    let hill_counts: Vec<usize> = hills.iter().map(Vec::len).collect();
+    let ant_counts: Vec<usize> = (0..players)
+        .map(|p| world.live_ants_for_player(p).len())
+        .collect();
+    if !all_equal(&hill_counts) || !all_equal(&ant_counts) {
+        problems.push(MapProblem::UnevenStart {
+            hills: hill_counts,
+            ants: ant_counts,
+        });
+    }
+
+    // Reachability
+    let all_hills: Vec<Position> = hills.iter().flatten().cloned().collect();
+    let from_any_hill = map.distances_from(&all_hills);
+    problems.extend(unreachable_regions(map, &from_any_hill));
+
+    let from_player: Vec<Vec<Option<u32>>> = hills.iter().map(|h| map.distances_from(h)).collect();
+    for (player, player_hills) in hills.iter().enumerate() {
+        for hill in player_hills {
+            let from_hill = map.distances_from(std::slice::from_ref(hill));
+            let reaches_enemy = hills
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != player)
+                .flat_map(|(_, h)| h)
+                .any(|h| from_hill[map.index(h)].is_some());
+            if !reaches_enemy && players > 1 {
+                problems.push(MapProblem::IsolatedHill {
+                    player: player as u8,
+                    hill: hill.clone(),
+                });
+            }
+        }
+    }
+
+    // Fairness
+    let per_player: Vec<Vec<u32>> = from_player
+        .iter()
+        .enumerate()
+        .map(|(player, distances)| {
+            let mut to_enemies: Vec<u32> = hills
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != player)
+                .flat_map(|(_, h)| h)
+                .map(|h| distances[map.index(h)].unwrap_or(u32::MAX))
+                .collect();
+            to_enemies.sort_unstable();
+            to_enemies
+        })
+        .collect();
+    let all_have_hills = hills.iter().all(|h| !h.is_empty());
+    if all_have_hills && !all_equal(&per_player) {
+        problems.push(MapProblem::AsymmetricHillDistances { per_player });
+    }
+
+    MapReport {
+        problems,
+        players: player_metrics(map, &from_player, steps),
+    }
+}
+
+fn all_equal<T: PartialEq>(items: &[T]) -> bool {
+    items.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+fn unreachable_regions(map: &Map, from_any_hill: &[Option<u32>]) -> Vec<MapProblem> {
+    let mut seen: Vec<bool> = from_any_hill.iter().map(Option::is_some).collect();
+    let mut problems = vec![];
+
+    for p in map.positions() {
+        let i = map.index(&p);
+        if seen[i] || !map.get(&p).is_passable() {
+            continue;
+        }
+        let region = map.distances_from(std::slice::from_ref(&p));
+        let mut size = 0;
+        for (j, distance) in region.iter().enumerate() {
+            if distance.is_some() {
+                seen[j] = true;
+                size += 1;
+            }
+        }
+        problems.push(MapProblem::UnreachableLand { size, example: p });
+    }
+    problems
+}
+
+fn player_metrics(map: &Map, from_player: &[Vec<Option<u32>>], steps: u32) -> Vec<PlayerMetrics> {
+    let mut shares = vec![0.0; from_player.len()];
+    let mut land_count = 0;
+
+    for p in map.positions() {
+        let i = map.index(&p);
+        if !map.get(&p).is_passable() {
+            continue;
+        }
+        land_count += 1;
+        let closest = from_player.iter().filter_map(|d| d[i]).min();
+        if let Some(closest) = closest {
+            let winners: Vec<usize> = (0..from_player.len())
+                .filter(|player| from_player[*player][i] == Some(closest))
+                .collect();
+            for player in winners.iter() {
+                shares[*player] += 1.0 / winners.len() as f64;
+            }
+        }
+    }
+
+    from_player
+        .iter()
+        .enumerate()
+        .map(|(player, distances)| PlayerMetrics {
+            player: player as u8,
+            reachable_area: distances
+                .iter()
+                .filter(|d| d.is_some_and(|d| d <= steps))
+                .count(),
+            food_share: if land_count == 0 {
+                0.0
+            } else {
+                shares[player] / land_count as f64
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapgen::{generate, MapGenParameters, MapStyle};
+    use super::super::pos;
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn generated_map_is_valid_and_fair() {
+        let params = MapGenParameters {
+            players: 4,
+            style: MapStyle::Cave,
+            ..MapGenParameters::default()
+        };
+        let report = validate(&generate(&params).unwrap(), 10);
+
+        assert_eq!(Vec::<MapProblem>::new(), report.problems);
+        assert_eq!(4, report.players.len());
+        for metrics in report.players.iter() {
+            assert_eq!(report.players[0].reachable_area, metrics.reachable_area);
+            assert!((metrics.food_share - 0.25).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn broken_map_problems() {
+        let map = Map::parse(indoc!(
+            "rows 5
+            cols 8
+            players 3
+            m A..%%...
+            m ...%.%..
+            m %%%%%%%%
+            m b.1...2.
+            m %%%%%%%%
+            "
+        ))
+        .unwrap();
+
+        let report = validate(&map, 3);
+
+        assert_eq!(
+            vec![
+                MapProblem::UnevenStart {
+                    hills: vec![1, 1, 1],
+                    ants: vec![1, 1, 0],
+                },
+                MapProblem::UnreachableLand {
+                    size: 1,
+                    example: pos(1, 4),
+                },
+                MapProblem::IsolatedHill {
+                    player: 0,
+                    hill: pos(0, 0),
+                },
+                MapProblem::AsymmetricHillDistances {
+                    per_player: vec![
+                        vec![u32::MAX, u32::MAX],
+                        vec![4, u32::MAX],
+                        vec![4, u32::MAX]
+                    ],
+                },
+            ],
+            report.problems
+        );
+        assert_eq!(
+            vec![11, 7, 7],
+            report
+                .players
+                .iter()
+                .map(|m| m.reachable_area)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unseen_tiles_are_not_land() {
+        let map = Map::parse("rows 1\ncols 6\nplayers 2\nm A.?B.?\n").unwrap();
+
+        let report = validate(&map, 2);
+
+        assert_eq!(0.5, report.players[0].food_share);
+        assert_eq!(0.5, report.players[1].food_share);
+    }
+
+    #[test]
+    fn header_problems() {
+        let map = Map::parse("rows 1\ncols 6\nplayers 2\nm A..C..\n").unwrap();
+
+        let report = validate(&map, 1);
+
+        assert_eq!(
+            vec![
+                MapProblem::UnknownPlayer {
+                    player: 2,
+                    players: 2
+                },
+                MapProblem::PlayerWithoutHill { player: 1 },
+                MapProblem::UnevenStart {
+                    hills: vec![1, 0],
+                    ants: vec![1, 0],
+                },
+                MapProblem::IsolatedHill {
+                    player: 0,
+                    hill: pos(0, 0),
+                },
+            ],
+            report.problems
+        );
+    }
+}
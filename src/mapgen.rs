@@ -0,0 +1,551 @@
+use super::map::{Map, Tile};
+use super::rng::Rng;
+use super::{pos, GameParameters, Position};
+use std::collections::VecDeque;
+
+/// Overall look of the generated water.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum MapStyle {
+    /// Mostly land with scattered lakes.
+    OpenField,
+    /// Organic caves, grown with a cellular automaton.
+    Cave,
+    /// Corridors of a braided maze.
+    Maze,
+}
+
+/// How the player starting positions mirror each other.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Symmetry {
+    /// Rotation around the map center, for 2 or 4 players. 4 players
+    /// requires a square map.
+    Rotational,
+    /// Mirroring in the map center lines, for 2 or 4 players.
+    Mirror,
+    /// Shifting along the wrapped map, for 2 to 10 players. Columns must
+    /// be divisible by the number of players.
+    Translational,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct MapGenParameters {
+    pub rows: u16,
+    pub cols: u16,
+    /// Number of players, 2 to 10.
+    pub players: u8,
+    pub hills_per_player: u8,
+    /// Approximate fraction of water tiles, 0.0 to 1.0.
+    pub water_density: f64,
+    pub style: MapStyle,
+    pub symmetry: Symmetry,
+    /// Seed for the generator. The same parameters always give the same map.
+    pub seed: i64,
+}
+
+impl Default for MapGenParameters {
+    fn default() -> Self {
+        MapGenParameters {
+            rows: 60,
+            cols: 60,
+            players: 2,
+            hills_per_player: 1,
+            water_density: 0.2,
+            style: MapStyle::OpenField,
+            symmetry: Symmetry::Translational,
+            seed: 0,
+        }
+    }
+}
+
+impl MapGenParameters {
+    /// Parameters for a map of the same size as the game, seeded with
+    /// `GameParameters::player_seed`.
+    pub fn for_game(params: &GameParameters, players: u8) -> Self {
+        MapGenParameters {
+            rows: params.rows as u16,
+            cols: params.cols as u16,
+            players,
+            seed: params.player_seed,
+            ..MapGenParameters::default()
+        }
+    }
+}
+
+const MAX_ATTEMPTS: u32 = 50;
+const HILL_CANDIDATES: u32 = 200;
+const MIN_HILL_DISTANCE2: i64 = 25;
+const MIN_LAND_FRACTION: f64 = 0.25;
+
+/// Generate a random but fair map.
+///
+/// Water, hills and initial ants (one on each hill) are placed
+/// symmetrically for all players, and all land is reachable from every
+/// hill.
+///
+/// # Example
+/// ```
+/// use ants_ai_challenge_api::mapgen::{generate, MapGenParameters};
+///
+/// let map = generate(&MapGenParameters::default()).unwrap();
+/// assert_eq!(2, map.players);
+/// assert_eq!(2, map.hills().len());
+/// ```
+pub fn generate(params: &MapGenParameters) -> Result<Map, String> {
+    if params.players < 2 || params.players > 10 {
+        return Err(format!("Unsupported player count {}", params.players));
+    }
+    if params.rows < 8 || params.cols < 8 {
+        return Err(format!(
+            "Map size {}x{} too small",
+            params.rows, params.cols
+        ));
+    }
+    if params.hills_per_player == 0 {
+        return Err(String::from("At least one hill per player is needed"));
+    }
+
+    let mut rng = Rng::new(params.seed);
+    let symmetry = SymmetryGroup::new(params, &mut rng)?;
+    let size = pos(params.rows, params.cols);
+
+    for _ in 0..MAX_ATTEMPTS {
+        let raw = match params.style {
+            MapStyle::OpenField => open_field(&size, params.water_density, &mut rng),
+            MapStyle::Cave => cave(&size, params.water_density, &mut rng),
+            MapStyle::Maze => maze(&size, params.water_density, &mut rng),
+        };
+        let mut water = symmetrize(&raw, &size, &symmetry);
+
+        let hills = match place_hills(&mut water, &size, &symmetry, params, &mut rng) {
+            Some(hills) => hills,
+            None => continue,
+        };
+
+        let reachable = flood_fill(&water, &size, &hills[0].0);
+        if hills.iter().any(|(h, _)| !reachable[index(&size, h)]) {
+            continue;
+        }
+        let land_count = reachable.iter().filter(|r| **r).count();
+        if (land_count as f64) < MIN_LAND_FRACTION * reachable.len() as f64 {
+            continue;
+        }
+
+        let mut map = Map::new(size.clone(), params.players);
+        for p in map.positions().collect::<Vec<_>>() {
+            if !reachable[index(&size, &p)] {
+                map.set(&p, Tile::Water);
+            }
+        }
+        for (hill, player) in hills {
+            map.set(&hill, Tile::AntOnHill(player));
+        }
+        return Ok(map);
+    }
+
+    Err(format!(
+        "Could not generate map in {} attempts, try other parameters",
+        MAX_ATTEMPTS
+    ))
+}
+
+/// The transforms mapping player 0's view of the map to each other
+/// player's view.
+struct SymmetryGroup {
+    size: Position,
+    players: u8,
+    kind: Symmetry,
+    row_step: u16,
+    col_step: u16,
+}
+
+impl SymmetryGroup {
+    fn new(params: &MapGenParameters, rng: &mut Rng) -> Result<Self, String> {
+        let players = params.players;
+        let (rows, cols) = (params.rows, params.cols);
+        let (mut row_step, mut col_step) = (0, 0);
+
+        match params.symmetry {
+            Symmetry::Translational => {
+                if cols % players as u16 != 0 {
+                    return Err(format!(
+                        "Columns {} not divisible by {} players",
+                        cols, players
+                    ));
+                }
+                col_step = cols / players as u16;
+                if rows % players as u16 == 0 {
+                    row_step = rng.below(players as u64) as u16 * (rows / players as u16);
+                }
+            }
+            Symmetry::Rotational if players == 4 && rows != cols => {
+                return Err(String::from("4 player rotation needs a square map"));
+            }
+            Symmetry::Rotational | Symmetry::Mirror if players != 2 && players != 4 => {
+                return Err(format!(
+                    "{:?} symmetry supports 2 or 4 players, not {}",
+                    params.symmetry, players
+                ));
+            }
+            _ => (),
+        }
+
+        Ok(SymmetryGroup {
+            size: pos(rows, cols),
+            players,
+            kind: params.symmetry,
+            row_step,
+            col_step,
+        })
+    }
+
+    /// Position as seen by given player.
+    fn apply(&self, player: u8, p: &Position) -> Position {
+        let (rows, cols) = (self.size.row, self.size.col);
+        let (r, c) = (p.row, p.col);
+        match (self.kind, self.players, player) {
+            (_, _, 0) => p.clone(),
+            (Symmetry::Translational, _, n) => self.size.as_size_for_pos(
+                r as i64 + n as i64 * self.row_step as i64,
+                c as i64 + n as i64 * self.col_step as i64,
+            ),
+            (Symmetry::Rotational, 2, _) => pos(rows - 1 - r, cols - 1 - c),
+            (Symmetry::Rotational, _, 1) => pos(c, rows - 1 - r),
+            (Symmetry::Rotational, _, 2) => pos(rows - 1 - r, cols - 1 - c),
+            (Symmetry::Rotational, _, _) => pos(cols - 1 - c, r),
+            (Symmetry::Mirror, _, 1) => pos(r, cols - 1 - c),
+            (Symmetry::Mirror, _, 2) => pos(rows - 1 - r, c),
+            (Symmetry::Mirror, _, _) => pos(rows - 1 - r, cols - 1 - c),
+        }
+    }
+
+    /// Position for each player, starting with player 0.
+    fn orbit(&self, p: &Position) -> Vec<Position> {
+        (0..self.players)
+            .map(|player| self.apply(player, p))
+            .collect()
+    }
+}
+
+fn index(size: &Position, p: &Position) -> usize {
+    p.row as usize * size.col as usize + p.col as usize
+}
+
+fn all_positions(size: &Position) -> impl Iterator<Item = Position> {
+    let size = size.clone();
+    (0..size.row).flat_map(move |row| (0..size.col).map(move |col| pos(row, col)))
+}
+
+/// Make water pattern symmetric, by letting each tile copy the pattern of
+/// the smallest position among its symmetric counterparts.
+fn symmetrize(raw: &[bool], size: &Position, symmetry: &SymmetryGroup) -> Vec<bool> {
+    all_positions(size)
+        .map(|p| {
+            let representative = symmetry.orbit(&p).into_iter().min().unwrap_or(p);
+            raw[index(size, &representative)]
+        })
+        .collect()
+}
+
+/// Pick symmetric hill positions, spread out as far as possible, and clear
+/// the water around them. Returns hill positions with their owners.
+fn place_hills(
+    water: &mut [bool],
+    size: &Position,
+    symmetry: &SymmetryGroup,
+    params: &MapGenParameters,
+    rng: &mut Rng,
+) -> Option<Vec<(Position, u8)>> {
+    let mut hills: Vec<(Position, u8)> = vec![];
+
+    for _ in 0..params.hills_per_player {
+        let mut best: Option<(i64, Vec<Position>)> = None;
+
+        for _ in 0..HILL_CANDIDATES {
+            let candidate = pos(
+                rng.below(size.row as u64) as u16,
+                rng.below(size.col as u64) as u16,
+            );
+            let orbit = symmetry.orbit(&candidate);
+            if orbit.iter().any(|p| water[index(size, p)]) {
+                continue;
+            }
+            let spread = orbit
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| orbit[i + 1..].iter().map(move |b| (a, b)))
+                .chain(
+                    orbit
+                        .iter()
+                        .flat_map(|a| hills.iter().map(move |(b, _)| (a, b))),
+                )
+                .map(|(a, b)| a.distance2(b, size))
+                .min()
+                .unwrap_or(0);
+            if best.as_ref().map_or(true, |(s, _)| spread > *s) {
+                best = Some((spread, orbit));
+            }
+        }
+
+        match best {
+            Some((spread, orbit)) if spread >= MIN_HILL_DISTANCE2 => {
+                for (player, hill) in orbit.into_iter().enumerate() {
+                    water[index(size, &hill)] = false;
+                    for n in hill.neighbours(size) {
+                        water[index(size, &n)] = false;
+                    }
+                    hills.push((hill, player as u8));
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    Some(hills)
+}
+
+/// Land tiles reachable from start.
+fn flood_fill(water: &[bool], size: &Position, start: &Position) -> Vec<bool> {
+    let mut reached = vec![false; water.len()];
+    let mut queue = VecDeque::new();
+    reached[index(size, start)] = true;
+    queue.push_back(start.clone());
+
+    while let Some(p) = queue.pop_front() {
+        for n in p.neighbours(size) {
+            let i = index(size, &n);
+            if !water[i] && !reached[i] {
+                reached[i] = true;
+                queue.push_back(n);
+            }
+        }
+    }
+    reached
+}
+
+/// Scattered round lakes of varying size.
+fn open_field(size: &Position, density: f64, rng: &mut Rng) -> Vec<bool> {
+    let tile_count = size.row as usize * size.col as usize;
+    let target = (density.clamp(0.0, 0.9) * tile_count as f64) as usize;
+    let mut water = vec![false; tile_count];
+    let mut water_count = 0;
+
+    while water_count < target {
+        let center = pos(
+            rng.below(size.row as u64) as u16,
+            rng.below(size.col as u64) as u16,
+        );
+        let radius = 1 + rng.below(3) as i64;
+        let radius2 = radius * radius + rng.below(radius as u64 + 1) as i64;
+        for d_row in -radius..=radius {
+            for d_col in -radius..=radius {
+                if d_row * d_row + d_col * d_col <= radius2 {
+                    let p =
+                        size.as_size_for_pos(center.row as i64 + d_row, center.col as i64 + d_col);
+                    let i = index(size, &p);
+                    if !water[i] {
+                        water[i] = true;
+                        water_count += 1;
+                    }
+                }
+            }
+        }
+    }
+    water
+}
+
+/// Caves grown from random noise by a cellular automaton.
+fn cave(size: &Position, density: f64, rng: &mut Rng) -> Vec<bool> {
+    let initial = (density + 0.1).clamp(0.0, 0.9);
+    let mut water: Vec<bool> = all_positions(size).map(|_| rng.chance(initial)).collect();
+
+    for _ in 0..4 {
+        water = all_positions(size)
+            .map(|p| {
+                let mut water_around = 0;
+                for d_row in -1..=1 {
+                    for d_col in -1..=1 {
+                        let n = size.as_size_for_pos(p.row as i64 + d_row, p.col as i64 + d_col);
+                        if (d_row, d_col) != (0, 0) && water[index(size, &n)] {
+                            water_around += 1;
+                        }
+                    }
+                }
+                water_around >= 5 || (water[index(size, &p)] && water_around >= 4)
+            })
+            .collect();
+    }
+    water
+}
+
+/// Braided maze with corridors three tiles wide. Lower water density
+/// removes more of the maze walls.
+fn maze(size: &Position, density: f64, rng: &mut Rng) -> Vec<bool> {
+    const CELL: u16 = 4;
+    let cell_rows = size.row / CELL;
+    let cell_cols = size.col / CELL;
+    let (maze_rows, maze_cols) = (cell_rows * CELL, cell_cols * CELL);
+
+    let mut water: Vec<bool> = all_positions(size)
+        .map(|p| p.row < maze_rows && p.col < maze_cols && (p.row % CELL == 0 || p.col % CELL == 0))
+        .collect();
+
+    // Wall segment on the south (vertical) or east (horizontal) side of a cell.
+    let carve = |water: &mut Vec<bool>, cell: &Position, south: bool| {
+        for offset in 1..CELL {
+            let p = if south {
+                pos(
+                    ((cell.row + 1) * CELL) % maze_rows,
+                    cell.col * CELL + offset,
+                )
+            } else {
+                pos(
+                    cell.row * CELL + offset,
+                    ((cell.col + 1) * CELL) % maze_cols,
+                )
+            };
+            water[index(size, &p)] = false;
+        }
+    };
+
+    // Randomized depth first search over the wrapped cell grid
+    let cell_size = pos(cell_rows.max(1), cell_cols.max(1));
+    let mut visited = vec![false; cell_size.row as usize * cell_size.col as usize];
+    let mut stack = vec![pos(0, 0)];
+    visited[0] = true;
+    while let Some(cell) = stack.last().cloned() {
+        let mut unvisited: Vec<Position> = cell
+            .neighbours(&cell_size)
+            .into_iter()
+            .filter(|n| !visited[index(&cell_size, n)])
+            .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+        rng.shuffle(&mut unvisited);
+        let next = unvisited[0].clone();
+        visited[index(&cell_size, &next)] = true;
+
+        // Carve the wall between the cells, owned by the upper or left cell.
+        if next.row != cell.row {
+            let upper = if (cell.row + 1) % cell_size.row == next.row {
+                &cell
+            } else {
+                &next
+            };
+            carve(&mut water, upper, true);
+        } else {
+            let left = if (cell.col + 1) % cell_size.col == next.col {
+                &cell
+            } else {
+                &next
+            };
+            carve(&mut water, left, false);
+        }
+        stack.push(next);
+    }
+
+    let removal = (1.0 - 2.0 * density).clamp(0.0, 1.0);
+    for cell in all_positions(&cell_size).collect::<Vec<_>>() {
+        for south in [true, false].iter() {
+            if rng.chance(removal) {
+                carve(&mut water, &cell, *south);
+            }
+        }
+    }
+    water
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(style: MapStyle, symmetry: Symmetry, players: u8) -> MapGenParameters {
+        MapGenParameters {
+            rows: 40,
+            cols: 40,
+            players,
+            water_density: 0.3,
+            style,
+            symmetry,
+            seed: 42,
+            ..MapGenParameters::default()
+        }
+    }
+
+    fn assert_fair(params: &MapGenParameters) {
+        let map = generate(params).expect("Generated map");
+        let symmetry = SymmetryGroup::new(params, &mut Rng::new(params.seed)).unwrap();
+
+        let hills = map.hills();
+        assert_eq!(params.players as usize, hills.len());
+        assert!(hills
+            .iter()
+            .all(|h| h.len() == params.hills_per_player as usize));
+
+        let is_water = |p: &Position| map.get(p) == Tile::Water;
+        for p in map.positions() {
+            for player in 0..params.players {
+                assert_eq!(
+                    is_water(&p),
+                    is_water(&symmetry.apply(player, &p)),
+                    "{:?} for {:?}",
+                    params,
+                    p
+                );
+            }
+        }
+
+        let water: Vec<bool> = map.positions().map(|p| is_water(&p)).collect();
+        let reachable = flood_fill(&water, &map.size, &hills[0][0]);
+        for p in map.positions() {
+            assert_eq!(!is_water(&p), reachable[index(&map.size, &p)]);
+        }
+    }
+
+    #[test]
+    fn generate_is_fair_for_all_styles_and_symmetries() {
+        for style in [MapStyle::OpenField, MapStyle::Cave, MapStyle::Maze].iter() {
+            for symmetry in [
+                Symmetry::Rotational,
+                Symmetry::Mirror,
+                Symmetry::Translational,
+            ]
+            .iter()
+            {
+                assert_fair(&params(*style, *symmetry, 2));
+                assert_fair(&params(*style, *symmetry, 4));
+            }
+        }
+        for players in [5, 8, 10].iter() {
+            let mut params = params(MapStyle::Cave, Symmetry::Translational, *players);
+            params.cols = 120;
+            assert_fair(&params);
+        }
+    }
+
+    #[test]
+    fn generate_is_deterministic() {
+        let params = params(MapStyle::Cave, Symmetry::Translational, 2);
+        let other_seed = MapGenParameters {
+            seed: 43,
+            ..params.clone()
+        };
+
+        assert_eq!(generate(&params), generate(&params));
+        assert_ne!(generate(&params), generate(&other_seed));
+    }
+
+    #[test]
+    fn generate_rejects_unsupported_parameters() {
+        assert!(generate(&params(MapStyle::Cave, Symmetry::Mirror, 3)).is_err());
+        assert!(generate(&params(MapStyle::Cave, Symmetry::Translational, 3)).is_err());
+        assert!(generate(&params(MapStyle::Cave, Symmetry::Translational, 11)).is_err());
+
+        let not_square = MapGenParameters {
+            cols: 60,
+            ..params(MapStyle::Cave, Symmetry::Rotational, 4)
+        };
+        assert!(generate(&not_square).is_err());
+    }
+}
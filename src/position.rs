@@ -45,7 +45,8 @@ impl Position {
     }
 
     /// Shorthand for order to pause (stay stationary), using
-    /// `Direction::NoDirection`.
+    /// `Direction::NoDirection`. Such holds are not sent to the server, but
+    /// are kept in replays to tell held ants from forgotten ones.
     pub fn pause(&self) -> Order {
         Order {
             pos: self.clone(),
@@ -68,29 +69,68 @@ impl Position {
     /// assert_eq!(pos(1, 3), foo.as_size_for_pos(11, -7));
     /// ```
     pub fn as_size_for_pos(&self, row: i64, col: i64) -> Position {
-        let mut mut_row = row;
-        let mut mut_col = col;
-
-        let size_row = self.row as i64;
-        let size_col = self.col as i64;
-
-        while mut_row < 0 {
-            mut_row += size_row;
-        }
-        while mut_col < 0 {
-            mut_col += size_col;
-        }
-
-        mut_row %= size_row;
-        mut_col %= size_col;
-
-        pos(mut_row as u16, mut_col as u16)
+        let wrapped_row = row.rem_euclid(self.row as i64);
+        let wrapped_col = col.rem_euclid(self.col as i64);
+        pos(wrapped_row as u16, wrapped_col as u16)
     }
 
     /// Use this position as size boundary for given argument.
     pub fn as_size_for(&self, pos_to_clamp: Position) -> Position {
         self.as_size_for_pos(pos_to_clamp.row.into(), pos_to_clamp.col.into())
     }
+
+    /// Neighbouring positions in all four directions, wrapped within the
+    /// given scope size.
+    pub fn neighbours(&self, scope_size: &Position) -> Vec<Position> {
+        Direction::ALL
+            .iter()
+            .map(|dir| self.order(*dir).target_pos(scope_size))
+            .collect()
+    }
+
+    /// Squared euclidean distance to other position, on a map that wraps
+    /// around at the given scope size.
+    ///
+    /// # Example
+    /// ```
+    /// use ants_ai_challenge_api::pos;
+    ///
+    /// let size = pos(10, 10);
+    /// assert_eq!(5, pos(1, 1).distance2(&pos(2, 3), &size));
+    /// assert_eq!(2, pos(0, 0).distance2(&pos(9, 9), &size));
+    /// ```
+    pub fn distance2(&self, other: &Position, scope_size: &Position) -> i64 {
+        fn wrapped_delta(a: u16, b: u16, size: u16) -> i64 {
+            let delta = (a as i64 - b as i64).abs();
+            std::cmp::min(delta, size as i64 - delta)
+        }
+        let d_row = wrapped_delta(self.row, other.row, scope_size.row);
+        let d_col = wrapped_delta(self.col, other.col, scope_size.col);
+        d_row * d_row + d_col * d_col
+    }
+}
+
+/// Row and column offsets of the tiles within a squared radius, e.g. the
+/// tiles in view or in attack range of an ant.
+///
+/// # Example
+/// ```
+/// use ants_ai_challenge_api::offsets;
+///
+/// assert_eq!(vec![(-1, 0), (0, -1), (0, 0), (0, 1), (1, 0)], offsets(1));
+/// assert_eq!(21, offsets(5).len());
+/// ```
+pub fn offsets(radius2: i64) -> Vec<(i64, i64)> {
+    let radius = (radius2.max(0) as f64).sqrt() as i64;
+    let mut result = vec![];
+    for d_row in -radius..=radius {
+        for d_col in -radius..=radius {
+            if d_row * d_row + d_col * d_col <= radius2 {
+                result.push((d_row, d_col));
+            }
+        }
+    }
+    result
 }
 
 impl fmt::Debug for Position {
@@ -110,6 +150,14 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// All directions an ant can move in.
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
     /// Reverse the direction
     ///
     /// East becomes West, North becomes South and vice versa.
@@ -175,6 +223,11 @@ impl Order {
         }
     }
 
+    /// Whether the order is a deliberate hold, made by `Position::pause`.
+    pub fn is_hold(&self) -> bool {
+        self.dir == Direction::NoDirection
+    }
+
     /// Reverse the order.
     ///
     /// An order East from position (1,1) is reversed
@@ -0,0 +1,244 @@
+use super::{
+    parse_order_line, serialize_end, serialize_turn_0, serialize_turn_x, Agent, GameParameters,
+    Orders, Score, WorldState,
+};
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// State of an external bot. As in the official rules, a bot that times
+/// out or crashes is out of the game: it is killed and its ants no longer
+/// receive any orders.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum BotStatus {
+    Alive,
+    /// Did not answer `go` within the load or turn time.
+    TimedOut,
+    /// Exited or closed its input or output.
+    Crashed,
+}
+
+/// Line based connection to a bot talking the game protocol, whether it
+/// runs as a process or connects over the network.
+pub(crate) struct BotConnection<W: Write> {
+    writer: Option<W>,
+    lines: Receiver<String>,
+    status: BotStatus,
+}
+
+impl<W: Write> BotConnection<W> {
+    /// Start reading lines from the bot on a separate thread, so that reads
+    /// can time out.
+    pub(crate) fn new<R: Read + Send + 'static>(writer: W, reader: R) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let sent = line.ok().map(|line| sender.send(line).is_ok());
+                if sent != Some(true) {
+                    break;
+                }
+            }
+        });
+        BotConnection {
+            writer: Some(writer),
+            lines,
+            status: BotStatus::Alive,
+        }
+    }
+
+    pub(crate) fn status(&self) -> BotStatus {
+        self.status
+    }
+
+    pub(crate) fn send(&mut self, text: &str) {
+        if self.status != BotStatus::Alive {
+            return;
+        }
+        let written = match self.writer.as_mut() {
+            Some(writer) => writer
+                .write_all(text.as_bytes())
+                .and_then(|_| writer.flush()),
+            None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+        };
+        if written.is_err() {
+            self.stop(BotStatus::Crashed);
+        }
+    }
+
+    /// Read orders until `go`. Lines that are not valid orders are ignored.
+    /// Without a timeout, waits for the bot however long it takes.
+    pub(crate) fn read_orders(&mut self, timeout_ms: Option<i64>) -> Orders {
+        let mut orders = vec![];
+        if self.status != BotStatus::Alive {
+            return orders;
+        }
+        let deadline =
+            timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms.max(0) as u64));
+
+        loop {
+            let line = match deadline {
+                Some(deadline) => self
+                    .lines
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .lines
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match line {
+                Ok(line) if line.trim() == "go" => return orders,
+                Ok(line) => orders.extend(parse_order_line(&line)),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.stop(BotStatus::TimedOut);
+                    return vec![];
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.stop(BotStatus::Crashed);
+                    return vec![];
+                }
+            }
+        }
+    }
+
+    /// Whether the bot is still connected, e.g. while it waits for a game.
+    /// Lines it sent in the meantime are dropped.
+    pub(crate) fn is_connected(&mut self) -> bool {
+        if self.status == BotStatus::Alive {
+            loop {
+                match self.lines.try_recv() {
+                    Ok(_) => (),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.stop(BotStatus::Crashed);
+                        break;
+                    }
+                }
+            }
+        }
+        self.status == BotStatus::Alive
+    }
+
+    /// Close the writing side, e.g. after the end of the game was sent.
+    pub(crate) fn close(&mut self) {
+        self.writer = None;
+    }
+
+    fn stop(&mut self, status: BotStatus) {
+        self.status = status;
+        self.writer = None;
+    }
+}
+
+/// Bot running as an external executable, talking the game protocol over
+/// its stdin and stdout.
+///
+/// # Example
+/// ```no_run
+/// use ants_ai_challenge_api::{run_game, ProcessBot};
+/// use std::path::Path;
+///
+/// let mut bot = ProcessBot::spawn("python3 MyBot.py", Some(Path::new("bot.log"))).unwrap();
+/// run_game(&mut bot);
+/// ```
+pub struct ProcessBot {
+    child: Child,
+    connection: BotConnection<ChildStdin>,
+    params: GameParameters,
+    time_limits: bool,
+}
+
+impl ProcessBot {
+    /// Start bot from a whitespace separated command line. Stderr of the bot
+    /// is appended to the given log file, or dropped if no file is given.
+    pub fn spawn(command_line: &str, stderr_log: Option<&Path>) -> io::Result<Self> {
+        let mut tokens = command_line.split_whitespace();
+        let program = tokens
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty bot command"))?;
+        Self::spawn_command(Command::new(program).args(tokens), stderr_log)
+    }
+
+    /// Start bot from a prepared command, e.g. with a working directory.
+    pub fn spawn_command(command: &mut Command, stderr_log: Option<&Path>) -> io::Result<Self> {
+        let stderr = match stderr_log {
+            Some(path) => Stdio::from(OpenOptions::new().create(true).append(true).open(path)?),
+            None => Stdio::null(),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("Piped stdin");
+        let stdout = child.stdout.take().expect("Piped stdout");
+
+        Ok(ProcessBot {
+            child,
+            connection: BotConnection::new(stdin, stdout),
+            params: GameParameters::default(),
+            time_limits: true,
+        })
+    }
+
+    /// Wait for the bot however long it takes, instead of stopping it at
+    /// the load or turn time, e.g. to measure its real latencies. It still
+    /// gets the time limits in the game parameters.
+    pub fn without_time_limits(mut self) -> Self {
+        self.time_limits = false;
+        self
+    }
+
+    pub fn status(&self) -> BotStatus {
+        self.connection.status()
+    }
+
+    /// Limit for a read, if time limits are enforced.
+    fn limit(&self, ms: i64) -> Option<i64> {
+        Some(ms).filter(|_| self.time_limits)
+    }
+
+    /// Kill the bot once it is out of the game.
+    fn kill_if_stopped(&mut self) {
+        if self.connection.status() != BotStatus::Alive {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+impl Agent for ProcessBot {
+    fn prepare(&mut self, params: GameParameters) {
+        self.connection.send(&serialize_turn_0(&params));
+        self.connection.read_orders(self.limit(params.loadtime_ms));
+        self.kill_if_stopped();
+        self.params = params;
+    }
+
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders {
+        self.connection.send(&serialize_turn_x(turn_count, world));
+        let orders = self
+            .connection
+            .read_orders(self.limit(self.params.turntime_ms));
+        self.kill_if_stopped();
+        orders
+    }
+
+    /// Send the final game state, after which the bot is expected to exit.
+    fn at_end(&mut self, world: WorldState, score: Score) {
+        self.connection.send(&serialize_end(&world, &score));
+        self.connection.close();
+    }
+}
+
+impl Drop for ProcessBot {
+    fn drop(&mut self) {
+        self.connection.close();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
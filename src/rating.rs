@@ -0,0 +1,513 @@
+use super::Score;
+use std::collections::BTreeMap;
+use std::f64::consts::SQRT_2;
+use std::fmt;
+use std::path::Path;
+
+/// Skill estimate of a single bot.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Rating {
+    /// TrueSkill mean.
+    pub mu: f64,
+    /// TrueSkill standard deviation.
+    pub sigma: f64,
+    pub elo: f64,
+    pub games: u32,
+}
+
+impl Rating {
+    /// Conservative TrueSkill estimate, as used for leaderboards.
+    pub fn conservative(&self) -> f64 {
+        self.mu - 3.0 * self.sigma
+    }
+}
+
+/// Rating system parameters. Defaults are the usual TrueSkill and Elo
+/// constants.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RatingParameters {
+    pub mu: f64,
+    pub sigma: f64,
+    /// Performance variation within a single game.
+    pub beta: f64,
+    /// Skill drift added before each game.
+    pub tau: f64,
+    /// Probability that two equally skilled bots draw.
+    pub draw_probability: f64,
+    pub elo: f64,
+    pub elo_k: f64,
+}
+
+impl Default for RatingParameters {
+    fn default() -> Self {
+        RatingParameters {
+            mu: 25.0,
+            sigma: 25.0 / 3.0,
+            beta: 25.0 / 6.0,
+            tau: 25.0 / 300.0,
+            draw_probability: 0.1,
+            elo: 1500.0,
+            elo_k: 32.0,
+        }
+    }
+}
+
+/// TrueSkill and Elo ratings for a set of named bots.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Ratings {
+    pub params: RatingParameters,
+    ratings: BTreeMap<String, Rating>,
+}
+
+impl Ratings {
+    pub fn new(params: RatingParameters) -> Self {
+        Ratings {
+            params,
+            ratings: BTreeMap::new(),
+        }
+    }
+
+    /// Rating of bot, or the initial rating for unknown bots.
+    pub fn get(&self, bot: &str) -> Rating {
+        self.ratings.get(bot).cloned().unwrap_or(Rating {
+            mu: self.params.mu,
+            sigma: self.params.sigma,
+            elo: self.params.elo,
+            games: 0,
+        })
+    }
+
+    /// All rated bots, best conservative TrueSkill estimate first.
+    pub fn leaderboard(&self) -> Vec<(String, Rating)> {
+        let mut board: Vec<(String, Rating)> = self
+            .ratings
+            .iter()
+            .map(|(name, rating)| (name.clone(), *rating))
+            .collect();
+        board.sort_by(|a, b| {
+            b.1.conservative()
+                .partial_cmp(&a.1.conservative())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        board
+    }
+
+    /// Update ratings with a game result given as score per bot. Higher
+    /// score is better and equal scores are ties.
+    pub fn update_with_score(&mut self, bots: &[&str], score: &Score) {
+        let scores = &score.per_player;
+        let ranks: Vec<usize> = scores
+            .iter()
+            .map(|a| scores.iter().filter(|b| *b > a).count())
+            .collect();
+        self.update(bots, &ranks);
+    }
+
+    /// Update ratings with a game result given as rank per bot, where zero
+    /// is the best and equal ranks are ties.
+    pub fn update(&mut self, bots: &[&str], ranks: &[usize]) {
+        assert_eq!(bots.len(), ranks.len(), "One rank per bot is needed");
+        if bots.len() < 2 {
+            return;
+        }
+        let before: Vec<Rating> = bots.iter().map(|b| self.get(b)).collect();
+        let skills = trueskill(&self.params, &before, ranks);
+        let elos = elo(&self.params, &before, ranks);
+
+        for (i, bot) in bots.iter().enumerate() {
+            self.ratings.insert(
+                bot.to_string(),
+                Rating {
+                    mu: skills[i].0,
+                    sigma: skills[i].1,
+                    elo: elos[i],
+                    games: before[i].games + 1,
+                },
+            );
+        }
+    }
+
+    /// Parse ratings written by `Display`, i.e. one bot per line with mu,
+    /// sigma, elo, games and name. Lines starting with `#` are ignored.
+    pub fn parse(text: &str, params: RatingParameters) -> Result<Self, String> {
+        let mut ratings = Ratings::new(params);
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.splitn(5, ' ');
+            let mut number = || {
+                tokens
+                    .next()
+                    .and_then(|t| t.parse::<f64>().ok())
+                    .ok_or_else(|| format!("Bad rating on line {}", index + 1))
+            };
+            let rating = Rating {
+                mu: number()?,
+                sigma: number()?,
+                elo: number()?,
+                games: number()? as u32,
+            };
+            let name = tokens
+                .next()
+                .ok_or_else(|| format!("Missing name on line {}", index + 1))?;
+            ratings.ratings.insert(name.to_string(), rating);
+        }
+        Ok(ratings)
+    }
+
+    /// Read ratings from file, or start empty if there is no file yet.
+    pub fn load(path: &Path, params: RatingParameters) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ratings::parse(&text, params),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Ratings::new(params)),
+            Err(e) => Err(format!("Could not read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Ratings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# mu sigma elo games name")?;
+        for (name, r) in self.ratings.iter() {
+            writeln!(f, "{} {} {} {} {}", r.mu, r.sigma, r.elo, r.games, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pairwise Elo, with the K factor shared among the opponents.
+fn elo(params: &RatingParameters, before: &[Rating], ranks: &[usize]) -> Vec<f64> {
+    let k = params.elo_k / (before.len() - 1) as f64;
+    (0..before.len())
+        .map(|i| {
+            let delta: f64 = (0..before.len())
+                .filter(|j| *j != i)
+                .map(|j| {
+                    let expected =
+                        1.0 / (1.0 + 10f64.powf((before[j].elo - before[i].elo) / 400.0));
+                    let actual = match ranks[i].cmp(&ranks[j]) {
+                        std::cmp::Ordering::Less => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.0,
+                    };
+                    actual - expected
+                })
+                .sum();
+            before[i].elo + k * delta
+        })
+        .collect()
+}
+
+/// Gaussian in natural parameters, precision and precision adjusted mean.
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Gaussian {
+    pi: f64,
+    tau: f64,
+}
+
+impl Gaussian {
+    const UNIFORM: Gaussian = Gaussian { pi: 0.0, tau: 0.0 };
+
+    fn new(mu: f64, sigma2: f64) -> Self {
+        Gaussian {
+            pi: 1.0 / sigma2,
+            tau: mu / sigma2,
+        }
+    }
+
+    fn mu(self) -> f64 {
+        if self.pi == 0.0 {
+            0.0
+        } else {
+            self.tau / self.pi
+        }
+    }
+
+    fn sigma2(self) -> f64 {
+        1.0 / self.pi
+    }
+
+    fn mul(self, other: Gaussian) -> Gaussian {
+        Gaussian {
+            pi: self.pi + other.pi,
+            tau: self.tau + other.tau,
+        }
+    }
+
+    fn div(self, other: Gaussian) -> Gaussian {
+        Gaussian {
+            pi: self.pi - other.pi,
+            tau: self.tau - other.tau,
+        }
+    }
+}
+
+const MAX_ITERATIONS: usize = 100;
+const EPSILON: f64 = 1e-6;
+
+/// TrueSkill update for a free-for-all game, by message passing on the
+/// chain of performance differences between bots in rank order. Returns
+/// new mu and sigma per bot.
+fn trueskill(params: &RatingParameters, before: &[Rating], ranks: &[usize]) -> Vec<(f64, f64)> {
+    let n = before.len();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|i| ranks[*i]);
+
+    let beta2 = params.beta * params.beta;
+    let draw_margin = inverse_cdf((params.draw_probability + 1.0) / 2.0) * SQRT_2 * params.beta;
+
+    // Skill priors and performance messages, in rank order
+    let priors: Vec<Gaussian> = order
+        .iter()
+        .map(|i| {
+            let r = &before[*i];
+            Gaussian::new(r.mu, r.sigma * r.sigma + params.tau * params.tau)
+        })
+        .collect();
+    let perf_priors: Vec<Gaussian> = priors
+        .iter()
+        .map(|p| Gaussian::new(p.mu(), p.sigma2() + beta2))
+        .collect();
+
+    // Messages from difference factor k to performances k and k + 1, and
+    // from truncation factor k to difference k.
+    let mut to_upper = vec![Gaussian::UNIFORM; n - 1];
+    let mut to_lower = vec![Gaussian::UNIFORM; n - 1];
+    let mut truncation = vec![Gaussian::UNIFORM; n - 1];
+
+    let perf_marginal = |k: usize, to_upper: &[Gaussian], to_lower: &[Gaussian]| {
+        let mut marginal = perf_priors[k];
+        if k > 0 {
+            marginal = marginal.mul(to_lower[k - 1]);
+        }
+        if k < n - 1 {
+            marginal = marginal.mul(to_upper[k]);
+        }
+        marginal
+    };
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut max_change: f64 = 0.0;
+        let schedule: Vec<usize> = (0..n - 1).chain((0..n - 1).rev()).collect();
+        for k in schedule {
+            let upper = perf_marginal(k, &to_upper, &to_lower).div(to_upper[k]);
+            let lower = perf_marginal(k + 1, &to_upper, &to_lower).div(to_lower[k]);
+
+            // Difference between the performances, then truncated
+            let diff = Gaussian::new(upper.mu() - lower.mu(), upper.sigma2() + lower.sigma2());
+            let is_draw = ranks[order[k]] == ranks[order[k + 1]];
+            let truncated = truncate(diff, draw_margin, is_draw);
+            let new_truncation = truncated.div(diff);
+            max_change = max_change
+                .max((new_truncation.pi - truncation[k].pi).abs())
+                .max((new_truncation.tau - truncation[k].tau).abs());
+            truncation[k] = new_truncation;
+
+            // Back to the performances
+            let from_truncation = truncation[k];
+            to_upper[k] = Gaussian::new(
+                from_truncation.mu() + lower.mu(),
+                from_truncation.sigma2() + lower.sigma2(),
+            );
+            to_lower[k] = Gaussian::new(
+                upper.mu() - from_truncation.mu(),
+                upper.sigma2() + from_truncation.sigma2(),
+            );
+        }
+        if max_change < EPSILON {
+            break;
+        }
+    }
+
+    let mut result = vec![(0.0, 0.0); n];
+    for (k, i) in order.iter().enumerate() {
+        let from_games = perf_marginal(k, &to_upper, &to_lower).div(perf_priors[k]);
+        let up = Gaussian::new(from_games.mu(), from_games.sigma2() + beta2);
+        let posterior = priors[k].mul(up);
+        result[*i] = (posterior.mu(), posterior.sigma2().sqrt());
+    }
+    result
+}
+
+/// Moment matched Gaussian for a difference that is known to be above the
+/// draw margin, or within it for a draw.
+fn truncate(diff: Gaussian, margin: f64, is_draw: bool) -> Gaussian {
+    let sqrt_pi = diff.pi.sqrt();
+    let t = diff.tau / sqrt_pi;
+    let e = margin * sqrt_pi;
+    let (v, w) = if is_draw {
+        (v_draw(t, e), w_draw(t, e))
+    } else {
+        (v_win(t, e), w_win(t, e))
+    };
+    Gaussian {
+        pi: diff.pi / (1.0 - w),
+        tau: (diff.tau + sqrt_pi * v) / (1.0 - w),
+    }
+}
+
+fn pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+fn cdf(x: f64) -> f64 {
+    erfc(-x / SQRT_2) / 2.0
+}
+
+/// Complementary error function, with fractional error below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + z / 2.0);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Inverse of the standard normal cumulative distribution, by bisection.
+fn inverse_cdf(p: f64) -> f64 {
+    let (mut low, mut high) = (-10.0, 10.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if cdf(mid) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+fn v_win(t: f64, e: f64) -> f64 {
+    let denominator = cdf(t - e);
+    if denominator < 2.222_758_749e-162 {
+        -t + e
+    } else {
+        pdf(t - e) / denominator
+    }
+}
+
+fn w_win(t: f64, e: f64) -> f64 {
+    let denominator = cdf(t - e);
+    if denominator < 2.222_758_749e-162 {
+        return if t < 0.0 { 1.0 } else { 0.0 };
+    }
+    let v = v_win(t, e);
+    v * (v + t - e)
+}
+
+fn v_draw(t: f64, e: f64) -> f64 {
+    let a = t.abs();
+    let denominator = cdf(e - a) - cdf(-e - a);
+    let v = if denominator < 2.222_758_749e-162 {
+        -a - e
+    } else {
+        (pdf(-e - a) - pdf(e - a)) / denominator
+    };
+    if t < 0.0 {
+        -v
+    } else {
+        v
+    }
+}
+
+fn w_draw(t: f64, e: f64) -> f64 {
+    let a = t.abs();
+    let denominator = cdf(e - a) - cdf(-e - a);
+    if denominator < 2.222_758_749e-162 {
+        return 1.0;
+    }
+    let v = v_draw(a, e);
+    v * v + ((e - a) * pdf(e - a) + (e + a) * pdf(e + a)) / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rating(expected_mu: f64, expected_sigma: f64, actual: Rating) {
+        assert!(
+            (expected_mu - actual.mu).abs() < 1e-3 && (expected_sigma - actual.sigma).abs() < 1e-3,
+            "expected mu {} sigma {}, got {:?}",
+            expected_mu,
+            expected_sigma,
+            actual
+        );
+    }
+
+    #[test]
+    fn trueskill_two_players() {
+        let mut ratings = Ratings::default();
+        ratings.update(&["a", "b"], &[0, 1]);
+
+        assert_rating(29.396, 7.171, ratings.get("a"));
+        assert_rating(20.604, 7.171, ratings.get("b"));
+        assert_eq!(1516.0, ratings.get("a").elo);
+        assert_eq!(1484.0, ratings.get("b").elo);
+        assert_eq!(1, ratings.get("a").games);
+        assert_eq!(0, ratings.get("c").games);
+    }
+
+    #[test]
+    fn trueskill_two_players_draw() {
+        let mut ratings = Ratings::default();
+        ratings.update_with_score(
+            &["a", "b"],
+            &Score {
+                per_player: vec![3, 3],
+            },
+        );
+
+        assert_rating(25.0, 6.458, ratings.get("a"));
+        assert_rating(25.0, 6.458, ratings.get("b"));
+        assert_eq!(1500.0, ratings.get("a").elo);
+    }
+
+    #[test]
+    fn trueskill_three_players() {
+        let mut ratings = Ratings::default();
+        ratings.update(&["b", "c", "a"], &[1, 2, 0]);
+
+        assert_rating(31.675, 6.6566, ratings.get("a"));
+        assert_rating(25.0, 6.208, ratings.get("b"));
+        assert_rating(18.325, 6.6566, ratings.get("c"));
+        assert_eq!(
+            vec!["a", "b", "c"],
+            ratings
+                .leaderboard()
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn display_then_parse() {
+        let mut ratings = Ratings::default();
+        ratings.update(&["bot v1", "bot v2", "other"], &[0, 1, 1]);
+
+        let text = ratings.to_string();
+        let parsed = Ratings::parse(&text, RatingParameters::default()).unwrap();
+
+        assert_eq!(ratings, parsed);
+        assert!(Ratings::parse("1 2 x 4 name", RatingParameters::default()).is_err());
+    }
+}
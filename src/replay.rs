@@ -0,0 +1,448 @@
+use super::debug::Annotation;
+use super::{Direction, GameParameters, Order, Orders, Position, WorldState};
+
+/// One recorded turn, i.e. what the agent saw and what it ordered.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct ReplayTurn {
+    pub world: WorldState,
+    pub orders: Orders,
+    /// Debug annotations made by the agent during the turn.
+    pub debug: Vec<Annotation>,
+}
+
+/// Recorded game, one entry per played turn.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct Replay {
+    pub params: GameParameters,
+    pub turns: Vec<ReplayTurn>,
+}
+
+impl Replay {
+    pub fn new(params: GameParameters) -> Self {
+        Replay {
+            params,
+            turns: vec![],
+        }
+    }
+
+    /// Add a recorded turn
+    pub fn turn(mut self, world: WorldState, orders: Orders) -> Self {
+        self.push_turn(world, orders);
+        self
+    }
+
+    /// Add a recorded turn in place
+    pub fn push_turn(&mut self, world: WorldState, orders: Orders) {
+        self.push_debug_turn(world, orders, vec![]);
+    }
+
+    /// Add a recorded turn, with debug annotations, in place
+    pub fn push_debug_turn(&mut self, world: WorldState, orders: Orders, debug: Vec<Annotation>) {
+        self.turns.push(ReplayTurn {
+            world,
+            orders,
+            debug,
+        });
+    }
+
+    pub fn max_player_count(&self) -> usize {
+        self.turns
+            .iter()
+            .map(|turn| turn.world.max_player_count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Render the replay as a single static HTML page with embedded
+    /// script. The page has no external references, so it can be opened
+    /// directly from disk.
+    pub fn to_html(&self) -> String {
+        HTML_TEMPLATE
+            .replace("/*REPLAY_DATA*/", &self.to_json())
+            .replace(
+                "<!--TITLE-->",
+                &format!("Ants replay, {} turns", self.turns.len()),
+            )
+    }
+
+    fn to_json(&self) -> String {
+        let turns: Vec<String> = self.turns.iter().map(turn_json).collect();
+        format!(
+            "{{\"rows\":{},\"cols\":{},\"players\":{},\"turns\":[{}]}}",
+            self.params.rows,
+            self.params.cols,
+            self.max_player_count(),
+            turns.join(",")
+        )
+    }
+}
+
+fn turn_json(turn: &ReplayTurn) -> String {
+    format!(
+        "{{\"food\":{},\"water\":{},\"ants\":{},\"dead\":{},\"hills\":{},\"orders\":{},\"debug\":{}}}",
+        positions_json(&turn.world.foods),
+        positions_json(&turn.world.waters),
+        per_player_json(&turn.world.live_ants),
+        per_player_json(&turn.world.dead_ants),
+        per_player_json(&turn.world.hills),
+        orders_json(&turn.orders),
+        annotations_json(&turn.debug)
+    )
+}
+
+fn positions_json(positions: &[Position]) -> String {
+    let items: Vec<String> = positions
+        .iter()
+        .map(|p| format!("[{},{}]", p.row, p.col))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn per_player_json(per_player: &[Vec<Position>]) -> String {
+    let items: Vec<String> = per_player.iter().map(|p| positions_json(p)).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn orders_json(orders: &[Order]) -> String {
+    let items: Vec<String> = orders
+        .iter()
+        .map(|o| {
+            let dir = match o.dir {
+                Direction::North => "N",
+                Direction::South => "S",
+                Direction::West => "W",
+                Direction::East => "E",
+                Direction::NoDirection => "-",
+            };
+            format!("[{},{},\"{}\"]", o.pos.row, o.pos.col, dir)
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn annotations_json(annotations: &[Annotation]) -> String {
+    let items: Vec<String> = annotations
+        .iter()
+        .map(|a| match a {
+            Annotation::TileColor { pos, color } => {
+                format!("[\"tile\",{},{},{}]", pos.row, pos.col, string_json(color))
+            }
+            Annotation::Arrow { from, to, color } => format!(
+                "[\"arrow\",{},{},{},{},{}]",
+                from.row,
+                from.col,
+                to.row,
+                to.col,
+                string_json(color)
+            ),
+            Annotation::Label { pos, text } => {
+                format!("[\"label\",{},{},{}]", pos.row, pos.col, string_json(text))
+            }
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+/// Quoted JSON string, also safe to embed inside a script element.
+fn string_json(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '<' => result.push_str("\\u003c"),
+            '>' => result.push_str("\\u003e"),
+            '&' => result.push_str("\\u0026"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title><!--TITLE--></title>
+<style>
+body { font-family: sans-serif; background: #222; color: #ddd; margin: 1em; }
+canvas { display: block; margin-bottom: 0.5em; background: #6b4e2e; }
+#graph { background: #111; cursor: pointer; }
+#controls button { min-width: 3em; }
+#scrub { width: 30em; vertical-align: middle; }
+#info { min-height: 1.5em; font-family: monospace; }
+</style>
+</head>
+<body>
+<canvas id="map"></canvas>
+<div id="controls">
+<button id="first" title="First turn">|&lt;</button>
+<button id="prev" title="Previous turn">&lt;</button>
+<button id="play" title="Play/pause (space)">play</button>
+<button id="next" title="Next turn">&gt;</button>
+<button id="last" title="Last turn">&gt;|</button>
+<input id="scrub" type="range" min="0" value="0">
+<span id="turn"></span>
+<select id="speed">
+<option value="400">slow</option>
+<option value="150" selected>normal</option>
+<option value="40">fast</option>
+</select>
+<label><input id="debug" type="checkbox" checked> debug</label>
+</div>
+<div id="info"></div>
+<canvas id="graph" width="600" height="150"></canvas>
+<script>
+"use strict";
+const data = /*REPLAY_DATA*/;
+const colors = ["#e41a1c", "#377eb8", "#4daf4a", "#984ea3", "#ff7f00",
+                "#ffff33", "#a65628", "#f781bf", "#999999", "#66c2a5"];
+const dirs = { N: [-1, 0], S: [1, 0], W: [0, -1], E: [0, 1] };
+const rows = Math.max(1, data.rows), cols = Math.max(1, data.cols);
+const last = Math.max(0, data.turns.length - 1);
+const tile = Math.max(2, Math.floor(Math.min(800 / cols, 600 / rows)));
+
+const map = document.getElementById("map");
+const mapCtx = map.getContext("2d");
+map.width = cols * tile;
+map.height = rows * tile;
+const graph = document.getElementById("graph");
+const graphCtx = graph.getContext("2d");
+const scrub = document.getElementById("scrub");
+scrub.max = last;
+
+// Water never goes away, so remember the first turn each water tile was seen.
+const waterSince = new Map();
+data.turns.forEach((t, i) => t.water.forEach(([r, c]) => {
+  const key = r * cols + c;
+  if (!waterSince.has(key)) waterSince.set(key, i);
+}));
+
+let turn = 0;
+let timer = null;
+
+function fillTile(r, c, color, inset) {
+  mapCtx.fillStyle = color;
+  mapCtx.fillRect(c * tile + inset, r * tile + inset, tile - 2 * inset, tile - 2 * inset);
+}
+
+function drawMap() {
+  mapCtx.clearRect(0, 0, map.width, map.height);
+  const t = data.turns[turn];
+  waterSince.forEach((since, key) => {
+    if (since <= turn) fillTile(Math.floor(key / cols), key % cols, "#1f4e9c", 0);
+  });
+  if (!t) return;
+  t.hills.forEach((hills, p) => hills.forEach(([r, c]) => {
+    fillTile(r, c, colors[p % colors.length], 0);
+    fillTile(r, c, "#6b4e2e", Math.max(1, tile / 5));
+  }));
+  t.food.forEach(([r, c]) => fillTile(r, c, "#f4f4f4", Math.max(1, tile / 3)));
+  t.dead.forEach((dead, p) => dead.forEach(([r, c]) => {
+    mapCtx.strokeStyle = colors[p % colors.length];
+    mapCtx.beginPath();
+    mapCtx.moveTo(c * tile, r * tile);
+    mapCtx.lineTo((c + 1) * tile, (r + 1) * tile);
+    mapCtx.moveTo((c + 1) * tile, r * tile);
+    mapCtx.lineTo(c * tile, (r + 1) * tile);
+    mapCtx.stroke();
+  }));
+  t.ants.forEach((ants, p) => ants.forEach(([r, c]) => {
+    mapCtx.fillStyle = colors[p % colors.length];
+    mapCtx.beginPath();
+    mapCtx.arc((c + 0.5) * tile, (r + 0.5) * tile, tile * 0.4, 0, 2 * Math.PI);
+    mapCtx.fill();
+  }));
+  mapCtx.strokeStyle = "#000";
+  t.orders.forEach(([r, c, d]) => {
+    const dir = dirs[d];
+    if (!dir) return;
+    mapCtx.beginPath();
+    mapCtx.moveTo((c + 0.5) * tile, (r + 0.5) * tile);
+    mapCtx.lineTo((c + 0.5 + dir[1] * 0.7) * tile, (r + 0.5 + dir[0] * 0.7) * tile);
+    mapCtx.stroke();
+  });
+  if (document.getElementById("debug").checked) drawDebug(t.debug);
+}
+
+function drawDebug(annotations) {
+  mapCtx.save();
+  mapCtx.globalAlpha = 0.6;
+  annotations.forEach(a => {
+    if (a[0] === "tile") {
+      fillTile(a[1], a[2], a[3], 0);
+    } else if (a[0] === "arrow") {
+      const x0 = (a[2] + 0.5) * tile, y0 = (a[1] + 0.5) * tile;
+      const x1 = (a[4] + 0.5) * tile, y1 = (a[3] + 0.5) * tile;
+      const angle = Math.atan2(y1 - y0, x1 - x0), head = Math.max(3, tile / 2);
+      mapCtx.strokeStyle = a[5];
+      mapCtx.beginPath();
+      mapCtx.moveTo(x0, y0);
+      mapCtx.lineTo(x1, y1);
+      mapCtx.lineTo(x1 - head * Math.cos(angle - 0.5), y1 - head * Math.sin(angle - 0.5));
+      mapCtx.moveTo(x1, y1);
+      mapCtx.lineTo(x1 - head * Math.cos(angle + 0.5), y1 - head * Math.sin(angle + 0.5));
+      mapCtx.stroke();
+    } else if (a[0] === "label") {
+      mapCtx.fillStyle = "#fff";
+      mapCtx.font = Math.max(8, tile) + "px monospace";
+      mapCtx.fillText(a[3], (a[2] + 1) * tile, (a[1] + 1) * tile);
+    }
+  });
+  mapCtx.restore();
+}
+
+function antCounts(p) {
+  return data.turns.map(t => (t.ants[p] || []).length);
+}
+
+function drawGraph() {
+  const w = graph.width, h = graph.height;
+  graphCtx.clearRect(0, 0, w, h);
+  let max = 1;
+  const counts = [];
+  for (let p = 0; p < data.players; p++) {
+    counts.push(antCounts(p));
+    max = Math.max(max, ...counts[p]);
+  }
+  const x = i => last === 0 ? 0 : i * (w - 1) / last;
+  const y = v => h - 1 - v * (h - 10) / max;
+  counts.forEach((series, p) => {
+    graphCtx.strokeStyle = colors[p % colors.length];
+    graphCtx.beginPath();
+    series.forEach((v, i) => i === 0 ? graphCtx.moveTo(x(i), y(v)) : graphCtx.lineTo(x(i), y(v)));
+    graphCtx.stroke();
+  });
+  graphCtx.strokeStyle = "#ddd";
+  graphCtx.beginPath();
+  graphCtx.moveTo(x(turn), 0);
+  graphCtx.lineTo(x(turn), h);
+  graphCtx.stroke();
+}
+
+function show(t) {
+  turn = Math.max(0, Math.min(last, t));
+  scrub.value = turn;
+  const counts = [];
+  for (let p = 0; p < data.players; p++) counts.push((data.turns[turn] ? antCounts(p)[turn] : 0));
+  document.getElementById("turn").textContent =
+    "turn " + (turn + 1) + " / " + data.turns.length + "  ants: " + counts.join(" ");
+  drawMap();
+  drawGraph();
+}
+
+function contents(r, c) {
+  const t = data.turns[turn];
+  const found = [];
+  const at = ([pr, pc]) => pr === r && pc === c;
+  const since = waterSince.get(r * cols + c);
+  if (since !== undefined && since <= turn) found.push("water");
+  if (!t) return found;
+  if (t.food.some(at)) found.push("food");
+  t.hills.forEach((hills, p) => { if (hills.some(at)) found.push("hill of player " + p); });
+  t.ants.forEach((ants, p) => { if (ants.some(at)) found.push("ant of player " + p); });
+  t.dead.forEach((dead, p) => { if (dead.some(at)) found.push("dead ant of player " + p); });
+  t.orders.forEach(o => { if (at(o)) found.push("order " + o[2]); });
+  t.debug.forEach(a => { if (a[0] === "label" && a[1] === r && a[2] === c) found.push("\"" + a[3] + "\""); });
+  return found;
+}
+
+function togglePlay() {
+  if (timer) {
+    clearInterval(timer);
+    timer = null;
+  } else {
+    if (turn >= last) show(0);
+    timer = setInterval(() => {
+      if (turn >= last) togglePlay(); else show(turn + 1);
+    }, Number(document.getElementById("speed").value));
+  }
+  document.getElementById("play").textContent = timer ? "pause" : "play";
+}
+
+document.getElementById("first").onclick = () => show(0);
+document.getElementById("prev").onclick = () => show(turn - 1);
+document.getElementById("play").onclick = togglePlay;
+document.getElementById("next").onclick = () => show(turn + 1);
+document.getElementById("last").onclick = () => show(last);
+document.getElementById("speed").onchange = () => { if (timer) { togglePlay(); togglePlay(); } };
+document.getElementById("debug").onchange = () => show(turn);
+scrub.oninput = () => show(Number(scrub.value));
+graph.onclick = e => show(Math.round(e.offsetX * last / (graph.width - 1)));
+map.onmousemove = e => {
+  const r = Math.floor(e.offsetY / tile), c = Math.floor(e.offsetX / tile);
+  const found = contents(r, c);
+  document.getElementById("info").textContent =
+    "(" + r + ", " + c + ") " + (found.length ? found.join(", ") : "land");
+};
+document.onkeydown = e => {
+  if (e.key === " ") { togglePlay(); e.preventDefault(); }
+  else if (e.key === "ArrowLeft") show(turn - 1);
+  else if (e.key === "ArrowRight") show(turn + 1);
+};
+show(0);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::*;
+
+    #[test]
+    fn to_json_success() {
+        let params = GameParameters {
+            rows: 3,
+            cols: 4,
+            ..GameParameters::default()
+        };
+        let world = WorldState::default()
+            .food(pos(0, 1))
+            .water(pos(2, 2))
+            .live_ant(pos(1, 1), 1)
+            .hill(pos(1, 2), 0);
+
+        let replay = Replay::new(params).turn(world, vec![pos(1, 1).north()]);
+
+        assert_eq!(
+            "{\"rows\":3,\"cols\":4,\"players\":2,\"turns\":[{\"food\":[[0,1]],\"water\":[[2,2]],\
+             \"ants\":[[],[[1,1]]],\"dead\":[],\"hills\":[[[1,2]]],\"orders\":[[1,1,\"N\"]],\"debug\":[]}]}",
+            replay.to_json()
+        );
+    }
+
+    #[test]
+    fn to_html_is_self_contained() {
+        let replay = Replay::new(GameParameters::default()).turn(WorldState::default(), vec![]);
+        let html = replay.to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("const data = {\"rows\":0"));
+        assert!(!html.contains("/*REPLAY_DATA*/"));
+        assert!(!html.contains("src="));
+        assert!(!html.contains("href="));
+    }
+
+    #[test]
+    fn annotations_json_escapes_strings() {
+        let annotations = vec![
+            Annotation::TileColor {
+                pos: pos(1, 2),
+                color: String::from("red"),
+            },
+            Annotation::Label {
+                pos: pos(3, 4),
+                text: String::from("\"</script>\""),
+            },
+        ];
+
+        assert_eq!(
+            "[[\"tile\",1,2,\"red\"],[\"label\",3,4,\"\\\"\\u003c/script\\u003e\\\"\"]]",
+            annotations_json(&annotations)
+        );
+    }
+}
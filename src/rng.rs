@@ -0,0 +1,70 @@
+/// Small deterministic pseudo random number generator (xorshift64*), so
+/// that generated content is reproducible from a seed such as
+/// `GameParameters::player_seed` on every platform.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: i64) -> Self {
+        // Scramble the seed with splitmix64, since xorshift needs a
+        // non-zero state and small seeds give poor first values.
+        let mut z = (seed as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 0x2545_f491_4f6c_dd1d } else { z },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform value in range `[0, bound)`. Bound must be positive.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// Uniform value in range `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// True with given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+
+        let seq_a: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let seq_b: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let seq_c: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+
+        assert_eq!(seq_a, seq_b);
+        assert_ne!(seq_a, seq_c);
+        assert!((0..1000).all(|_| a.below(7) < 7));
+    }
+}
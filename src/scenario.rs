@@ -0,0 +1,286 @@
+//! Test helper for describing a turn as an ASCII picture, using the
+//! characters of the map file format.
+//!
+//! # Example
+//! ```
+//! use ants_ai_challenge_api::*;
+//!
+//! /// Walks every ant east.
+//! struct Eastward;
+//!
+//! impl Agent for Eastward {
+//!     fn prepare(&mut self, _params: GameParameters) {}
+//!
+//!     fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+//!         world.live_ants_for_player(0).iter().map(|a| a.order(East)).collect()
+//!     }
+//! }
+//!
+//! assert_orders!(Eastward, "..a.*\n%%0..\n", contains: "o 0 2 E");
+//! assert_orders!(Eastward, "..a.*\n%%0..\n", excludes: "o 0 2 W", "o 1 2 E");
+//! ```
+
+use super::map::{Map, MapParseError, Tile};
+#[cfg(test)]
+use super::map_memory::MapMemory;
+use super::{parse_order_line, pos, Agent, GameParameters, Order, Orders, WorldState};
+
+/// Parameters and world for a single turn.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Scenario {
+    pub params: GameParameters,
+    pub world: WorldState,
+    pub turn: u32,
+}
+
+impl Scenario {
+    /// Parse picture with one line per map row. Leading and trailing
+    /// whitespace and empty lines are ignored, so pictures can be indented.
+    /// Everything in the picture is visible: water, food, hills and ants,
+    /// with player 0 being the agent. Parameters are the official ones with
+    /// the size of the picture, and the turn is 1.
+    pub fn parse(picture: &str) -> Result<Scenario, MapParseError> {
+        let map = parse_picture(picture)?;
+        Ok(Scenario {
+            params: GameParameters {
+                rows: map.size.row as i64,
+                cols: map.size.col as i64,
+                ..GameParameters::official()
+            },
+            world: map.to_world_state(),
+            turn: 1,
+        })
+    }
+
+    /// Use given parameters, except for the map size of the picture.
+    pub fn params(self, params: GameParameters) -> Self {
+        Scenario {
+            params: GameParameters {
+                rows: self.params.rows,
+                cols: self.params.cols,
+                ..params
+            },
+            ..self
+        }
+    }
+
+    pub fn turn(self, turn: u32) -> Self {
+        Scenario { turn, ..self }
+    }
+
+    /// Prepare agent and let it make the turn.
+    pub fn run(&self, agent: &mut dyn Agent) -> Orders {
+        agent.prepare(self.params.clone());
+        agent.make_turn(&self.world, self.turn)
+    }
+}
+
+#[cfg(test)]
+impl Scenario {
+    /// Parse picture for a unit test, with the parameters changed from the
+    /// official ones. Panics if the picture can not be parsed.
+    pub(crate) fn with_params(picture: &str, change: impl FnOnce(&mut GameParameters)) -> Self {
+        let mut params = GameParameters::official();
+        change(&mut params);
+        picture.into_scenario().params(params)
+    }
+
+    /// Memory of having seen the world of the scenario.
+    pub(crate) fn memory(&self) -> MapMemory {
+        let mut memory = MapMemory::new(&self.params);
+        memory.update(&self.world, self.turn);
+        memory
+    }
+}
+
+/// World of a picture for a unit test. Panics if the picture can not be
+/// parsed.
+#[cfg(test)]
+pub(crate) fn world(picture: &str) -> WorldState {
+    picture.into_scenario().world
+}
+
+/// Anything `assert_orders!` accepts as scenario. Pictures panic if they
+/// can not be parsed.
+pub trait IntoScenario {
+    fn into_scenario(self) -> Scenario;
+}
+
+impl IntoScenario for Scenario {
+    fn into_scenario(self) -> Scenario {
+        self
+    }
+}
+
+impl IntoScenario for &Scenario {
+    fn into_scenario(self) -> Scenario {
+        self.clone()
+    }
+}
+
+impl IntoScenario for &str {
+    fn into_scenario(self) -> Scenario {
+        Scenario::parse(self).unwrap_or_else(|e| panic!("Bad scenario picture, {}", e))
+    }
+}
+
+/// Parse order in protocol format, e.g. `o 0 2 E`. Panics on bad orders.
+pub fn order(text: &str) -> Order {
+    parse_order_line(text).unwrap_or_else(|| panic!("Bad order {:?}", text))
+}
+
+/// Map from picture, with as many players as the highest owner in it.
+fn parse_picture(picture: &str) -> Result<Map, MapParseError> {
+    let mut rows: Vec<Vec<Tile>> = vec![];
+    for (index, line) in picture.lines().enumerate() {
+        let error = |message: String| MapParseError {
+            line: index + 1,
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let tiles = line
+            .chars()
+            .map(|c| {
+                Tile::from_char(c).ok_or_else(|| error(format!("Unknown map character {:?}", c)))
+            })
+            .collect::<Result<Vec<Tile>, _>>()?;
+        if let Some(first) = rows.first() {
+            if first.len() != tiles.len() {
+                return Err(error(format!(
+                    "Row of length {} differs from first row of length {}",
+                    tiles.len(),
+                    first.len()
+                )));
+            }
+        }
+        rows.push(tiles);
+    }
+    if rows.is_empty() {
+        return Err(MapParseError {
+            line: picture.lines().count(),
+            message: "Empty picture".to_string(),
+        });
+    }
+
+    let players = rows
+        .iter()
+        .flat_map(|tiles| tiles.iter())
+        .filter_map(|t| match t {
+            Tile::Hill(o) | Tile::Ant(o) | Tile::AntOnHill(o) => Some(o + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(1);
+    let mut map = Map::new(pos(rows.len() as u16, rows[0].len() as u16), players);
+    for (row, tiles) in rows.iter().enumerate() {
+        for (col, tile) in tiles.iter().enumerate() {
+            map.set(&pos(row as u16, col as u16), *tile);
+        }
+    }
+    Ok(map)
+}
+
+/// Assert on the orders an agent gives in a scenario, given as picture or
+/// `Scenario`. Expected orders are written in protocol format.
+///
+/// - `contains:` all the orders are given,
+/// - `excludes:` none of the orders are given,
+/// - `equals:` exactly the orders are given, in any order.
+///
+/// See the `scenario` module for an example.
+#[macro_export]
+macro_rules! assert_orders {
+    ($agent:expr, $scenario:expr, contains: $($order:expr),+ $(,)?) => {{
+        let scenario = $crate::scenario::IntoScenario::into_scenario($scenario);
+        let orders = scenario.run(&mut $agent);
+        $(
+            let expected = $crate::scenario::order($order);
+            assert!(
+                orders.contains(&expected),
+                "Expected order {:?} in {:?}, for {:?}",
+                expected, orders, scenario.world
+            );
+        )+
+    }};
+    ($agent:expr, $scenario:expr, excludes: $($order:expr),+ $(,)?) => {{
+        let scenario = $crate::scenario::IntoScenario::into_scenario($scenario);
+        let orders = scenario.run(&mut $agent);
+        $(
+            let unexpected = $crate::scenario::order($order);
+            assert!(
+                !orders.contains(&unexpected),
+                "Unexpected order {:?} in {:?}, for {:?}",
+                unexpected, orders, scenario.world
+            );
+        )+
+    }};
+    ($agent:expr, $scenario:expr, equals: $($order:expr),* $(,)?) => {{
+        let scenario = $crate::scenario::IntoScenario::into_scenario($scenario);
+        let mut orders = scenario.run(&mut $agent);
+        let mut expected: $crate::Orders = vec![$($crate::scenario::order($order)),*];
+        orders.sort();
+        expected.sort();
+        assert_eq!(expected, orders, "Orders for {:?}", scenario.world);
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parse_picture_success() {
+        let scenario = Scenario::parse(indoc!(
+            "
+            ..a.*
+            %%0.B
+            "
+        ))
+        .unwrap();
+
+        let expected = WorldState::default()
+            .water(pos(1, 0))
+            .water(pos(1, 1))
+            .food(pos(0, 4))
+            .hill(pos(1, 2), 0)
+            .hill(pos(1, 4), 1)
+            .live_ant(pos(0, 2), 0)
+            .live_ant(pos(1, 4), 1);
+        assert_eq!(expected, scenario.world);
+        assert_eq!(2, scenario.params.rows);
+        assert_eq!(5, scenario.params.cols);
+        assert_eq!(
+            GameParameters::official().viewradius2,
+            scenario.params.viewradius2
+        );
+        assert_eq!(1, scenario.turn);
+    }
+
+    #[test]
+    fn parse_picture_failure() {
+        assert_eq!(2, Scenario::parse("..\n.x\n").unwrap_err().line);
+        assert_eq!(3, Scenario::parse("..\n\n...\n").unwrap_err().line);
+        assert!(Scenario::parse("  \n").is_err());
+    }
+
+    #[test]
+    fn params_keep_picture_size() {
+        let scenario = Scenario::parse("...")
+            .unwrap()
+            .turn(7)
+            .params(GameParameters {
+                rows: 40,
+                attackradius2: 9,
+                ..GameParameters::default()
+            });
+
+        assert_eq!(1, scenario.params.rows);
+        assert_eq!(3, scenario.params.cols);
+        assert_eq!(9, scenario.params.attackradius2);
+        assert_eq!(7, scenario.turn);
+    }
+}
@@ -0,0 +1,173 @@
+//! Proptest strategies for protocol and geometry types, enabled with the
+//! `proptest` feature.
+//!
+//! # Example
+//! ```
+//! # #[cfg(feature = "proptest")]
+//! # {
+//! use ants_ai_challenge_api::pos;
+//! use ants_ai_challenge_api::strategies::order;
+//! use proptest::strategy::{Strategy, ValueTree};
+//! use proptest::test_runner::TestRunner;
+//!
+//! let mut runner = TestRunner::default();
+//! let order = order(pos(10, 20)).new_tree(&mut runner).unwrap().current();
+//! assert!(order.pos.row < 10 && order.pos.col < 20);
+//! # }
+//! ```
+
+use super::map::Map;
+use super::{
+    pos, serialize_end, serialize_turn_0, serialize_turn_x, Direction, GameParameters, Order,
+    Position, Score, WorldState,
+};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// Position within a map of given size.
+pub fn position(size: Position) -> impl Strategy<Value = Position> {
+    (0..size.row.max(1), 0..size.col.max(1)).prop_map(|(row, col)| pos(row, col))
+}
+
+/// One of the four directions an ant can move in.
+pub fn direction() -> impl Strategy<Value = Direction> {
+    select(Direction::ALL.to_vec())
+}
+
+/// Order for a position within a map of given size.
+pub fn order(size: Position) -> impl Strategy<Value = Order> {
+    (position(size), direction()).prop_map(|(p, dir)| p.order(dir))
+}
+
+/// Game parameters in the ranges of real games.
+pub fn game_parameters() -> impl Strategy<Value = GameParameters> {
+    (
+        0..10_000i64,
+        0..5_000i64,
+        1..200i64,
+        1..200i64,
+        1..2_000i64,
+        0..200i64,
+        0..20i64,
+        0..10i64,
+        any::<i64>(),
+    )
+        .prop_map(
+            |(loadtime, turntime, rows, cols, turns, view, attack, spawn, seed)| GameParameters {
+                loadtime_ms: loadtime,
+                turntime_ms: turntime,
+                rows,
+                cols,
+                turns,
+                viewradius2: view,
+                attackradius2: attack,
+                spawnradius2: spawn,
+                player_seed: seed,
+            },
+        )
+}
+
+/// What a land tile holds in a generated world.
+#[derive(Debug, Clone, Copy)]
+enum Content {
+    Nothing,
+    Food,
+    Ant(u8),
+    DeadAnt(u8),
+}
+
+/// World as seen in a turn on the map: any water of the map, any hills of
+/// the map, and food, live and dead ants of the map players on land.
+pub fn world_state(map: &Map) -> impl Strategy<Value = WorldState> {
+    let players = map.players.max(1);
+    let waters: Vec<Position> = map
+        .positions()
+        .filter(|p| !map.get(p).is_passable())
+        .collect();
+    let land: Vec<Position> = map
+        .positions()
+        .filter(|p| map.get(p).is_passable())
+        .collect();
+    let hills: Vec<(Position, u8)> = map
+        .hills()
+        .into_iter()
+        .enumerate()
+        .flat_map(|(player, hills)| hills.into_iter().map(move |h| (h, player as u8)))
+        .collect();
+
+    let content = prop_oneof![
+        4 => Just(Content::Nothing),
+        1 => Just(Content::Food),
+        2 => (0..players).prop_map(Content::Ant),
+        1 => (0..players).prop_map(Content::DeadAnt),
+    ];
+    (
+        vec(any::<bool>(), waters.len()),
+        vec(any::<bool>(), hills.len()),
+        vec(content, land.len()),
+    )
+        .prop_map(move |(seen_waters, seen_hills, contents)| {
+            let mut world = WorldState::default();
+            for (p, _) in waters.iter().zip(seen_waters).filter(|(_, seen)| *seen) {
+                world = world.water(p.clone());
+            }
+            for ((p, owner), _) in hills.iter().zip(seen_hills).filter(|(_, seen)| *seen) {
+                world = world.hill(p.clone(), *owner);
+            }
+            for (p, c) in land.iter().zip(contents) {
+                world = match c {
+                    Content::Nothing => world,
+                    Content::Food => world.food(p.clone()),
+                    Content::Ant(owner) => world.live_ant(p.clone(), owner),
+                    Content::DeadAnt(owner) => world.dead_ant(p.clone(), owner),
+                };
+            }
+            world
+        })
+}
+
+/// Everything a bot receives during a game.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Transcript {
+    pub params: GameParameters,
+    /// World of each turn, starting with turn 1.
+    pub turns: Vec<WorldState>,
+    pub end_world: WorldState,
+    pub score: Score,
+}
+
+impl Transcript {
+    /// Protocol lines sent to the bot, without line endings.
+    pub fn lines(&self) -> Vec<String> {
+        let mut text = serialize_turn_0(&self.params);
+        for (i, world) in self.turns.iter().enumerate() {
+            text += &serialize_turn_x(i as u32 + 1, world);
+        }
+        text += &serialize_end(&self.end_world, &self.score);
+        text.lines().map(String::from).collect()
+    }
+}
+
+/// Valid game on the map, lasting 1 to `max_turns` turns.
+pub fn transcript(map: &Map, max_turns: usize) -> impl Strategy<Value = Transcript> {
+    let size = map.size.clone();
+    let players = map.players.max(1) as usize;
+    (
+        game_parameters(),
+        vec(world_state(map), 1..=max_turns.max(1)),
+        world_state(map),
+        vec(0..100u64, players),
+    )
+        .prop_map(move |(params, turns, end_world, score)| Transcript {
+            params: GameParameters {
+                rows: size.row as i64,
+                cols: size.col as i64,
+                turns: turns.len() as i64,
+                ..params
+            },
+            turns,
+            end_world,
+            score: Score { per_player: score },
+        })
+}
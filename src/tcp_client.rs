@@ -0,0 +1,102 @@
+//! Client for the community TCP servers of the challenge, see
+//! `tcp_server` for the protocol.
+
+use super::{play_game, Agent, Replay, Score};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Game played over a connection, from the perspective of the agent, i.e.
+/// the agent is player 0. Unlike `engine::GameResult` it has no time outs,
+/// as the client can not tell whether other players timed out.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ClientGameResult {
+    /// Final score, indexed by player number as seen by the agent.
+    pub score: Score,
+    /// Number of turns played.
+    pub turns: u32,
+    /// Replay of the game as seen by the agent.
+    pub replay: Replay,
+}
+
+/// Connect to a game server speaking the protocol of the community TCP
+/// servers, log in and play the given number of consecutive games.
+///
+/// Lost connections are retried a few times before giving up, as are games
+/// lost due to the connection dropping. Returns a result per finished game.
+pub fn run_game_tcp<A: ToSocketAddrs>(
+    agent: &mut dyn Agent,
+    addr: A,
+    user: &str,
+    password: &str,
+    games: usize,
+) -> std::io::Result<Vec<ClientGameResult>> {
+    let mut results = vec![];
+    let mut failures = 0;
+    while results.len() < games {
+        match play_game_tcp(agent, &addr, user, password) {
+            Ok(Some(result)) => {
+                failures = 0;
+                results.push(result);
+            }
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => return Err(e),
+            Err(e) if failures >= TCP_RETRIES => return Err(e),
+            Ok(None) if failures >= TCP_RETRIES => {
+                return Err(Error::new(
+                    ErrorKind::ConnectionAborted,
+                    "Connection dropped",
+                ))
+            }
+            _ => {
+                failures += 1;
+                std::thread::sleep(TCP_RETRY_DELAY);
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Times a connection is retried in a row.
+const TCP_RETRIES: u32 = 5;
+const TCP_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Play a single game over a new connection, or none if the connection
+/// dropped before the game ended.
+fn play_game_tcp<A: ToSocketAddrs>(
+    agent: &mut dyn Agent,
+    addr: &A,
+    user: &str,
+    password: &str,
+) -> std::io::Result<Option<ClientGameResult>> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(format!("USER {} {}\n", user, password).as_bytes())?;
+
+    let mut lines_in = BufReader::new(stream.try_clone()?)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).into_owned())
+        .filter(|line| !line.starts_with("INFO:"))
+        .peekable();
+    match lines_in.peek() {
+        Some(line) if line.starts_with("ERROR:") => {
+            return Err(Error::new(ErrorKind::PermissionDenied, line.clone()))
+        }
+        Some(_) => (),
+        None => return Ok(None),
+    }
+
+    let mut out = |line: String| {
+        let _ = stream.write_all(line.as_bytes());
+    };
+    let mut replay = Replay::default();
+    let (_, score) = match play_game(agent, &mut lines_in, &mut out, Some(&mut replay)) {
+        Ok(Some(end)) => end,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(Error::new(ErrorKind::InvalidData, e)),
+    };
+    Ok(Some(ClientGameResult {
+        turns: replay.turns.len() as u32,
+        score,
+        replay,
+    }))
+}
@@ -0,0 +1,446 @@
+//! Game server speaking the protocol of the community TCP servers of the
+//! challenge.
+//!
+//! A bot connects and logs in with `USER name password`. The first login
+//! of a name registers its password. Logged in bots wait until enough
+//! players are available for the next map in rotation, after which the
+//! game is played over the connection with the usual game protocol and the
+//! connection is closed. Bots reconnect to play again. Lines sent by the
+//! server outside of games start with `INFO:` or `ERROR:`.
+
+use super::engine::play_game;
+use super::map::Map;
+use super::process_bot::{BotConnection, BotStatus};
+use super::rating::{RatingParameters, Ratings};
+use super::{
+    serialize_end, serialize_turn_0, serialize_turn_x, Agent, GameParameters, Orders, Score,
+    WorldState,
+};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Number of finished games listed on the status page.
+const RECENT_GAMES: usize = 50;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ServerParameters {
+    /// Parameters for every game. The seed is varied between games.
+    pub game: GameParameters,
+    /// Maps played in rotation.
+    pub maps: Vec<Map>,
+    /// Directory for HTML replays of finished games, if any.
+    pub replay_dir: Option<PathBuf>,
+    /// File keeping ratings between server runs, if any.
+    pub ratings_file: Option<PathBuf>,
+    /// Time for a connected bot to log in.
+    pub login_timeout_ms: u64,
+}
+
+impl ServerParameters {
+    pub fn new(maps: Vec<Map>) -> Self {
+        ServerParameters {
+            game: GameParameters::official(),
+            maps,
+            replay_dir: None,
+            ratings_file: None,
+            login_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Finished game.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GameRecord {
+    pub id: usize,
+    /// Index of map in the server parameters.
+    pub map: usize,
+    /// Player names, in map player order.
+    pub players: Vec<String>,
+    pub score: Score,
+    pub turns: u32,
+    /// Whether the replay was written to the replay directory.
+    pub has_replay: bool,
+}
+
+/// Shared handle to a running server.
+#[derive(Clone)]
+pub struct GameServer {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    params: ServerParameters,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    passwords: BTreeMap<String, String>,
+    waiting: Vec<(String, TcpBot)>,
+    next_map: usize,
+    started: usize,
+    running: Vec<(usize, Vec<String>)>,
+    games: Vec<GameRecord>,
+    ratings: Ratings,
+    errors: Vec<String>,
+}
+
+impl State {
+    /// Forget waiting bots that disconnected before their game started.
+    fn drop_disconnected(&mut self) {
+        self.waiting
+            .retain_mut(|(_, bot)| bot.connection.is_connected());
+    }
+}
+
+impl GameServer {
+    pub fn new(params: ServerParameters) -> Result<Self, String> {
+        if params.maps.is_empty() {
+            return Err("At least one map is needed".to_string());
+        }
+        let ratings = match params.ratings_file.as_ref() {
+            Some(path) => Ratings::load(path, RatingParameters::default())?,
+            None => Ratings::default(),
+        };
+        Ok(GameServer {
+            shared: Arc::new(Shared {
+                params,
+                state: Mutex::new(State {
+                    ratings,
+                    ..State::default()
+                }),
+            }),
+        })
+    }
+
+    /// Accept bots until the listener fails, playing games on separate
+    /// threads.
+    pub fn serve_players(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || server.login(stream));
+        }
+        Ok(())
+    }
+
+    /// Serve the status page and replays over HTTP until the listener fails.
+    pub fn serve_status(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || server.respond_http(stream));
+        }
+        Ok(())
+    }
+
+    /// Finished games, oldest first.
+    pub fn games(&self) -> Vec<GameRecord> {
+        self.state().games.clone()
+    }
+
+    pub fn ratings(&self) -> Ratings {
+        self.state().ratings.clone()
+    }
+
+    /// Replays and ratings that could not be written, oldest first.
+    pub fn errors(&self) -> Vec<String> {
+        self.state().errors.clone()
+    }
+
+    /// HTML page with ratings, waiting bots, running and recent games.
+    pub fn status_html(&self) -> String {
+        let mut state = self.state();
+        state.drop_disconnected();
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Ants server</title></head><body>\n",
+        );
+
+        html += "<h1>Ratings</h1>\n<table><tr><th>rank</th><th>bot</th><th>games</th><th>mu</th><th>sigma</th><th>elo</th></tr>\n";
+        for (rank, (name, rating)) in state.ratings.leaderboard().iter().enumerate() {
+            html += &format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{:.0}</td></tr>\n",
+                rank + 1,
+                escape_html(name),
+                rating.games,
+                rating.mu,
+                rating.sigma,
+                rating.elo
+            );
+        }
+        html += "</table>\n";
+
+        let waiting: Vec<String> = state.waiting.iter().map(|(n, _)| escape_html(n)).collect();
+        html += &format!("<h1>Waiting</h1>\n<p>{}</p>\n", waiting.join(", "));
+
+        html += "<h1>Running</h1>\n<ul>\n";
+        for (id, players) in state.running.iter() {
+            let players: Vec<String> = players.iter().map(|n| escape_html(n)).collect();
+            html += &format!("<li>Game {}: {}</li>\n", id, players.join(", "));
+        }
+        html += "</ul>\n";
+
+        html += "<h1>Finished</h1>\n<table><tr><th>game</th><th>map</th><th>turns</th><th>players</th></tr>\n";
+        for game in state.games.iter().rev().take(RECENT_GAMES) {
+            let id = if game.has_replay {
+                format!("<a href=\"/replays/{0}.html\">{0}</a>", game.id)
+            } else {
+                game.id.to_string()
+            };
+            let players: Vec<String> = game
+                .players
+                .iter()
+                .zip(game.score.per_player.iter())
+                .map(|(name, score)| format!("{} ({})", escape_html(name), score))
+                .collect();
+            html += &format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                id,
+                game.map,
+                game.turns,
+                players.join(", ")
+            );
+        }
+        html += "</table>\n";
+
+        if !state.errors.is_empty() {
+            html += "<h1>Errors</h1>\n<ul>\n";
+            for error in state.errors.iter() {
+                html += &format!("<li>{}</li>\n", escape_html(error));
+            }
+            html += "</ul>\n";
+        }
+        html += "</body></html>\n";
+        html
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.shared.state.lock().expect("Server state")
+    }
+
+    fn login(&self, mut stream: TcpStream) {
+        let timeout = Duration::from_millis(self.shared.params.login_timeout_ms.max(1));
+        if stream.set_read_timeout(Some(timeout)).is_err() {
+            return;
+        }
+        let mut reader = match stream.try_clone() {
+            Ok(reader) => BufReader::new(reader),
+            Err(_) => return,
+        };
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            return;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (name, password) = match tokens.as_slice() {
+            ["USER", name, password] => (name.to_string(), password.to_string()),
+            _ => {
+                let _ = stream.write_all(b"ERROR: expected USER name password\n");
+                return;
+            }
+        };
+        {
+            let mut state = self.state();
+            let known = state
+                .passwords
+                .entry(name.clone())
+                .or_insert_with(|| password.clone());
+            if *known != password {
+                let _ = stream.write_all(b"ERROR: wrong password\n");
+                return;
+            }
+        }
+        let greeting = format!("INFO: logged in as {}, waiting for game\n", name);
+        if stream.write_all(greeting.as_bytes()).is_err() || stream.set_read_timeout(None).is_err()
+        {
+            return;
+        }
+
+        let bot = match TcpBot::new(stream, reader) {
+            Ok(bot) => bot,
+            Err(_) => return,
+        };
+        self.state().waiting.push((name, bot));
+        self.start_games();
+    }
+
+    /// Start games on the next maps while there are enough distinct bots
+    /// waiting and still connected, in order of arrival.
+    fn start_games(&self) {
+        let mut state = self.state();
+        state.drop_disconnected();
+        loop {
+            let map = state.next_map % self.shared.params.maps.len();
+            let players = self.shared.params.maps[map].players as usize;
+
+            let mut seats: Vec<usize> = vec![];
+            for (i, (name, _)) in state.waiting.iter().enumerate() {
+                if seats.len() < players && seats.iter().all(|s| state.waiting[*s].0 != *name) {
+                    seats.push(i);
+                }
+            }
+            if seats.len() < players {
+                return;
+            }
+
+            let mut bots = vec![];
+            for seat in seats.iter().rev() {
+                bots.push(state.waiting.remove(*seat));
+            }
+            bots.reverse();
+            let id = state.started;
+            state.started += 1;
+            state.next_map += 1;
+            state
+                .running
+                .push((id, bots.iter().map(|(n, _)| n.clone()).collect()));
+
+            let server = self.clone();
+            thread::spawn(move || server.run_game(id, map, bots));
+        }
+    }
+
+    fn run_game(&self, id: usize, map: usize, bots: Vec<(String, TcpBot)>) {
+        let (names, mut agents): (Vec<String>, Vec<TcpBot>) = bots.into_iter().unzip();
+        let mut agent_refs: Vec<&mut dyn Agent> =
+            agents.iter_mut().map(|a| a as &mut dyn Agent).collect();
+        let params = &self.shared.params;
+        let game_params = GameParameters {
+            player_seed: params.game.player_seed + id as i64,
+            ..params.game.clone()
+        };
+        let result = play_game(&params.maps[map], &game_params, &mut agent_refs);
+        drop(agents);
+
+        let replay_written = params.replay_dir.as_ref().map(|dir| {
+            let file = dir.join(format!("{}.html", id));
+            std::fs::write(&file, result.replay.to_html())
+                .map_err(|e| format!("Could not write {}: {}", file.display(), e))
+        });
+
+        let mut state = self.state();
+        let has_replay = match replay_written {
+            Some(Ok(())) => true,
+            Some(Err(error)) => {
+                state.errors.push(error);
+                false
+            }
+            None => false,
+        };
+        let name_refs: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        state.ratings.update_with_score(&name_refs, &result.score);
+        if let Some(path) = params.ratings_file.as_ref() {
+            if let Err(e) = state.ratings.save(path) {
+                let error = format!("Could not write {}: {}", path.display(), e);
+                state.errors.push(error);
+            }
+        }
+        state.running.retain(|(running, _)| *running != id);
+        state.games.push(GameRecord {
+            id,
+            map,
+            players: names,
+            score: result.score,
+            turns: result.turns,
+            has_replay,
+        });
+    }
+
+    fn respond_http(&self, mut stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+        let mut reader = match stream.try_clone() {
+            Ok(reader) => BufReader::new(reader),
+            Err(_) => return,
+        };
+        let mut request = String::new();
+        if reader.read_line(&mut request).is_err() {
+            return;
+        }
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+        let page = if path == "/" {
+            Some(self.status_html())
+        } else {
+            path.strip_prefix("/replays/")
+                .and_then(|file| file.strip_suffix(".html"))
+                .and_then(|id| id.parse::<usize>().ok())
+                .and_then(|id| {
+                    let dir = self.shared.params.replay_dir.as_ref()?;
+                    std::fs::read_to_string(dir.join(format!("{}.html", id))).ok()
+                })
+        };
+        let response = match page {
+            Some(body) => format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+            None => "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+        };
+        let _ = stream.write_all(response.as_bytes());
+    }
+}
+
+/// Bot connected over the network.
+struct TcpBot {
+    stream: TcpStream,
+    connection: BotConnection<TcpStream>,
+    params: GameParameters,
+}
+
+impl TcpBot {
+    fn new<R: Read + Send + 'static>(stream: TcpStream, reader: R) -> io::Result<Self> {
+        Ok(TcpBot {
+            connection: BotConnection::new(stream.try_clone()?, reader),
+            stream,
+            params: GameParameters::default(),
+        })
+    }
+
+    /// Disconnect the bot once it is out of the game.
+    fn disconnect_if_stopped(&mut self) {
+        if self.connection.status() != BotStatus::Alive {
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Agent for TcpBot {
+    fn prepare(&mut self, params: GameParameters) {
+        self.connection.send(&serialize_turn_0(&params));
+        self.connection.read_orders(Some(params.loadtime_ms));
+        self.disconnect_if_stopped();
+        self.params = params;
+    }
+
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders {
+        self.connection.send(&serialize_turn_x(turn_count, world));
+        let orders = self.connection.read_orders(Some(self.params.turntime_ms));
+        self.disconnect_if_stopped();
+        orders
+    }
+
+    fn at_end(&mut self, world: WorldState, score: Score) {
+        self.connection.send(&serialize_end(&world, &score));
+        self.connection.close();
+    }
+}
+
+impl Drop for TcpBot {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
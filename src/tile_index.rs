@@ -0,0 +1,167 @@
+//! Flat tile indexes, for inner loops over whole maps (path finding,
+//! combat) that keep their data in plain arrays instead of working with
+//! positions.
+
+use super::{pos, Direction, Position};
+
+/// Row major index of a tile, `row * cols + col`, the same as `Map::index`.
+#[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Default, Hash, Clone, Copy)]
+pub struct TileIndex(pub u32);
+
+impl TileIndex {
+    /// Index into flat per tile arrays.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Size of a wrapped map, with neighbour tables for moving between tile
+/// indexes without any wrapping arithmetic. Making one fills the tables, so
+/// make it once per game and keep it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MapSize {
+    rows: u16,
+    cols: u16,
+    /// Neighbour of each tile, in the order of `Direction::ALL`.
+    neighbours: [Vec<TileIndex>; 4],
+}
+
+impl MapSize {
+    /// Size of a map of given rows and columns, as used for `scope_size`.
+    pub fn new(size: &Position) -> Self {
+        let (rows, cols) = (size.row, size.col);
+        let mut map_size = MapSize {
+            rows,
+            cols,
+            neighbours: Default::default(),
+        };
+        for (k, dir) in Direction::ALL.iter().enumerate() {
+            map_size.neighbours[k] = map_size
+                .indexes()
+                .map(|i| map_size.index_of(&map_size.position(i).order(*dir).target_pos(size)))
+                .collect();
+        }
+        map_size
+    }
+
+    pub fn rows(&self) -> u16 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u16 {
+        self.cols
+    }
+
+    /// Size as a position, as used for `scope_size`.
+    pub fn as_position(&self) -> Position {
+        pos(self.rows, self.cols)
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.rows as usize * self.cols as usize
+    }
+
+    /// All tile indexes, row by row.
+    pub fn indexes(&self) -> impl Iterator<Item = TileIndex> {
+        (0..self.tile_count() as u32).map(TileIndex)
+    }
+
+    pub fn index_of(&self, p: &Position) -> TileIndex {
+        TileIndex(p.row as u32 * self.cols as u32 + p.col as u32)
+    }
+
+    pub fn position(&self, i: TileIndex) -> Position {
+        let cols = self.cols as u32;
+        pos((i.0 / cols) as u16, (i.0 % cols) as u16)
+    }
+
+    /// Tile one step in the direction, wrapping around the edges of the
+    /// map. `NoDirection` stays on the tile.
+    pub fn neighbour(&self, i: TileIndex, dir: Direction) -> TileIndex {
+        let table = match dir {
+            Direction::North => &self.neighbours[0],
+            Direction::East => &self.neighbours[1],
+            Direction::South => &self.neighbours[2],
+            Direction::West => &self.neighbours[3],
+            Direction::NoDirection => return i,
+        };
+        table[i.index()]
+    }
+
+    /// Neighbours in all four directions, in the order of `Direction::ALL`.
+    pub fn neighbours(&self, i: TileIndex) -> [TileIndex; 4] {
+        let n = i.index();
+        [
+            self.neighbours[0][n],
+            self.neighbours[1][n],
+            self.neighbours[2][n],
+            self.neighbours[3][n],
+        ]
+    }
+
+    /// Tile at any row and column, wrapped into the map.
+    pub fn wrap(&self, row: i64, col: i64) -> TileIndex {
+        let row = row.rem_euclid(self.rows as i64) as u32;
+        let col = col.rem_euclid(self.cols as i64) as u32;
+        TileIndex(row * self.cols as u32 + col)
+    }
+
+    /// Tile at the given row and column offset from another, wrapped into
+    /// the map, e.g. for the offsets within a radius.
+    pub fn offset(&self, i: TileIndex, d_row: i64, d_col: i64) -> TileIndex {
+        let cols = self.cols as u32;
+        self.wrap((i.0 / cols) as i64 + d_row, (i.0 % cols) as i64 + d_col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::*;
+
+    #[test]
+    fn converts_positions() {
+        let size = MapSize::new(&pos(3, 5));
+
+        assert_eq!(TileIndex(7), size.index_of(&pos(1, 2)));
+        assert_eq!(pos(2, 4), size.position(TileIndex(14)));
+        for i in size.indexes() {
+            assert_eq!(i, size.index_of(&size.position(i)));
+        }
+        assert_eq!(15, size.indexes().count());
+        assert_eq!(pos(3, 5), size.as_position());
+    }
+
+    #[test]
+    fn neighbours_match_orders() {
+        let scope_size = pos(3, 5);
+        let size = MapSize::new(&scope_size);
+
+        for i in size.indexes() {
+            let p = size.position(i);
+            for dir in [North, East, South, West, NoDirection].iter() {
+                assert_eq!(
+                    p.order(*dir).target_pos(&scope_size),
+                    size.position(size.neighbour(i, *dir))
+                );
+            }
+            let expected: Vec<TileIndex> = p
+                .neighbours(&scope_size)
+                .iter()
+                .map(|n| size.index_of(n))
+                .collect();
+            assert_eq!(expected, size.neighbours(i).to_vec());
+        }
+    }
+
+    #[test]
+    fn offsets_wrap() {
+        let size = MapSize::new(&pos(10, 10));
+        let i = size.index_of(&pos(1, 1));
+
+        assert_eq!(pos(1, 3), size.position(size.wrap(11, -7)));
+        assert_eq!(pos(9, 0), size.position(size.wrap(-31, 1000)));
+        assert_eq!(pos(0, 8), size.position(size.offset(i, -1, -3)));
+        assert_eq!(i, size.offset(i, 20, -30));
+    }
+}
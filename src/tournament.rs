@@ -0,0 +1,534 @@
+use super::engine::{play_game, GameResult};
+use super::map::Map;
+use super::{Agent, GameParameters, ProcessBot};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Creates a fresh in-process agent for every game.
+pub type AgentFactory = Arc<dyn Fn() -> Box<dyn Agent> + Send + Sync>;
+
+#[derive(Clone)]
+pub enum BotKind {
+    InProcess(AgentFactory),
+    /// Whitespace separated command line of an external bot.
+    Command(String),
+}
+
+/// Tournament participant.
+#[derive(Clone)]
+pub struct Bot {
+    pub name: String,
+    pub kind: BotKind,
+}
+
+impl Bot {
+    pub fn in_process<F>(name: &str, factory: F) -> Self
+    where
+        F: Fn() -> Box<dyn Agent> + Send + Sync + 'static,
+    {
+        Bot {
+            name: name.to_string(),
+            kind: BotKind::InProcess(Arc::new(factory)),
+        }
+    }
+
+    pub fn command(name: &str, command_line: &str) -> Self {
+        Bot {
+            name: name.to_string(),
+            kind: BotKind::Command(command_line.to_string()),
+        }
+    }
+
+    pub(crate) fn create_agent(&self) -> Result<Box<dyn Agent>, String> {
+        match &self.kind {
+            BotKind::InProcess(factory) => Ok(factory()),
+            BotKind::Command(command_line) => ProcessBot::spawn(command_line, None)
+                .map(|bot| Box::new(bot) as Box<dyn Agent>)
+                .map_err(|e| format!("Could not start bot {}: {}", self.name, e)),
+        }
+    }
+}
+
+impl fmt::Debug for Bot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            BotKind::InProcess(_) => write!(f, "Bot {{ {}, in process }}", self.name),
+            BotKind::Command(c) => write!(f, "Bot {{ {}, command {:?} }}", self.name, c),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Pairing {
+    /// Every combination of bots plays once on every map.
+    RoundRobin,
+    /// Bots with similar standing play each other, for a number of rounds.
+    /// Each round uses the next map in the pool.
+    Swiss { rounds: u32 },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TournamentParameters {
+    /// Parameters for every game. The seed is varied between games.
+    pub game: GameParameters,
+    pub pairing: Pairing,
+    /// Number of games played in parallel.
+    pub threads: usize,
+}
+
+impl Default for TournamentParameters {
+    fn default() -> Self {
+        TournamentParameters {
+            game: GameParameters::official(),
+            pairing: Pairing::RoundRobin,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// Scheduled game, with the map index in the pool and the bot index for
+/// each map player.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct Match {
+    pub map: usize,
+    pub seats: Vec<usize>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Standing {
+    pub name: String,
+    pub games: u32,
+    /// One point for each opponent with lower score in a game, and half a
+    /// point for each opponent with equal score.
+    pub points: f64,
+    pub total_score: u64,
+    pub timeouts: u32,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct TournamentResult {
+    /// Standings, best first.
+    pub standings: Vec<Standing>,
+    pub games: Vec<(Match, GameResult)>,
+    /// Games that could not be played, e.g. due to bots failing to start.
+    pub errors: Vec<String>,
+}
+
+/// All combinations of bots for every map with at most that many bots.
+///
+/// # Example
+/// ```
+/// use ants_ai_challenge_api::tournament::round_robin;
+///
+/// let matches = round_robin(3, &[2]);
+/// let seats: Vec<Vec<usize>> = matches.into_iter().map(|m| m.seats).collect();
+/// assert_eq!(vec![vec![0, 1], vec![0, 2], vec![1, 2]], seats);
+/// ```
+pub fn round_robin(bot_count: usize, players_per_map: &[u8]) -> Vec<Match> {
+    let mut matches = vec![];
+    for (map, players) in players_per_map.iter().enumerate() {
+        for seats in combinations(bot_count, *players as usize) {
+            matches.push(Match { map, seats });
+        }
+    }
+    matches
+}
+
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if k > n {
+        return vec![];
+    }
+    let mut result = combinations(n - 1, k);
+    for mut combination in combinations(n - 1, k - 1) {
+        combination.push(n - 1);
+        result.push(combination);
+    }
+    result.sort();
+    result
+}
+
+/// Number of steps spent looking for a Swiss round without rematches
+/// before settling for one with rematches.
+const SWISS_SEARCH_LIMIT: u32 = 10_000;
+
+/// Swiss pairing: bots in standing order are grouped into games on the
+/// round's map, each with the best ranked open bot and the next open bots
+/// that have not all played together before. Bots left over get a bye,
+/// which goes to bots with the most games so far, lowest ranked first, so
+/// that it rotates. If no such round is found, groups follow the standing
+/// order, rematches or not.
+fn swiss_round(
+    ranking: &[usize],
+    round: u32,
+    players_per_map: &[u8],
+    played: &[Match],
+) -> Vec<Match> {
+    let map = round as usize % players_per_map.len();
+    let players = players_per_map[map] as usize;
+
+    let mut games = vec![0; ranking.len()];
+    for game in played {
+        for bot in game.seats.iter() {
+            games[*bot] += 1;
+        }
+    }
+    let most_games = games.iter().copied().max().unwrap_or(0);
+    let byes = ranking.len() % players;
+    let met: HashSet<Vec<usize>> = played.iter().map(|game| sorted(&game.seats)).collect();
+
+    let mut steps = SWISS_SEARCH_LIMIT;
+    let groups = group_without_rematches(
+        ranking,
+        players,
+        byes,
+        &|bot| games[bot] == most_games,
+        &met,
+        &mut steps,
+    )
+    .unwrap_or_else(|| {
+        let mut sitting_out: Vec<usize> = ranking.iter().rev().copied().collect();
+        sitting_out.sort_by_key(|bot| std::cmp::Reverse(games[*bot]));
+        sitting_out.truncate(byes);
+        let open: Vec<usize> = ranking
+            .iter()
+            .filter(|bot| !sitting_out.contains(bot))
+            .copied()
+            .collect();
+        open.chunks(players).map(|group| group.to_vec()).collect()
+    });
+    groups
+        .into_iter()
+        .map(|seats| Match { map, seats })
+        .collect()
+}
+
+/// Depth first search for groups of the open bots, in order, where no group
+/// has met before and the given number of bots sit out. `None` if there is
+/// no such grouping or the steps run out.
+fn group_without_rematches(
+    open: &[usize],
+    players: usize,
+    byes: usize,
+    may_sit_out: &dyn Fn(usize) -> bool,
+    met: &HashSet<Vec<usize>>,
+    steps: &mut u32,
+) -> Option<Vec<Vec<usize>>> {
+    let (first, rest) = match open.split_first() {
+        Some(split) => split,
+        None => return Some(vec![]),
+    };
+    if *steps == 0 {
+        return None;
+    }
+    *steps -= 1;
+
+    if open.len() >= byes + players {
+        for others in combinations(rest.len(), players - 1) {
+            let mut seats = vec![*first];
+            seats.extend(others.iter().map(|i| rest[*i]));
+            if met.contains(&sorted(&seats)) {
+                continue;
+            }
+            let remaining: Vec<usize> = rest
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !others.contains(i))
+                .map(|(_, bot)| *bot)
+                .collect();
+            if let Some(mut groups) =
+                group_without_rematches(&remaining, players, byes, may_sit_out, met, steps)
+            {
+                groups.insert(0, seats);
+                return Some(groups);
+            }
+        }
+    }
+    if byes > 0 && may_sit_out(*first) {
+        return group_without_rematches(rest, players, byes - 1, may_sit_out, met, steps);
+    }
+    None
+}
+
+fn sorted(seats: &[usize]) -> Vec<usize> {
+    let mut seats = seats.to_vec();
+    seats.sort_unstable();
+    seats
+}
+
+/// Play a tournament between the bots on the maps.
+pub fn run_tournament(
+    bots: &[Bot],
+    maps: &[Map],
+    params: &TournamentParameters,
+) -> TournamentResult {
+    let mut result = TournamentResult {
+        standings: bots
+            .iter()
+            .map(|bot| Standing {
+                name: bot.name.clone(),
+                ..Standing::default()
+            })
+            .collect(),
+        ..TournamentResult::default()
+    };
+    if maps.is_empty() {
+        return result;
+    }
+    let players_per_map: Vec<u8> = maps.iter().map(|m| m.players).collect();
+
+    let rounds = match params.pairing {
+        Pairing::RoundRobin => vec![round_robin(bots.len(), &players_per_map)],
+        Pairing::Swiss { rounds } => (0..rounds).map(|_| vec![]).collect(),
+    };
+    let mut played = vec![];
+    for (round, scheduled) in rounds.into_iter().enumerate() {
+        let matches = match params.pairing {
+            Pairing::RoundRobin => scheduled,
+            Pairing::Swiss { .. } => swiss_round(
+                &ranking(&result.standings),
+                round as u32,
+                &players_per_map,
+                &played,
+            ),
+        };
+        played.extend(matches.iter().cloned());
+        let first_seed = params.game.player_seed + result.games.len() as i64;
+        for (game, outcome) in run_matches(bots, maps, matches, params, first_seed) {
+            match outcome {
+                Ok(game_result) => {
+                    update_standings(&mut result.standings, &game, &game_result);
+                    result.games.push((game, game_result));
+                }
+                Err(error) => result.errors.push(error),
+            }
+        }
+    }
+
+    result.standings = ranking(&result.standings)
+        .into_iter()
+        .map(|i| result.standings[i].clone())
+        .collect();
+    result
+}
+
+/// Bot indexes ordered by standing, best first.
+fn ranking(standings: &[Standing]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..standings.len()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (&standings[*a], &standings[*b]);
+        b.points
+            .partial_cmp(&a.points)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.total_score.cmp(&a.total_score))
+    });
+    order
+}
+
+fn update_standings(standings: &mut [Standing], game: &Match, result: &GameResult) {
+    let scores = &result.score.per_player;
+    for (seat, bot) in game.seats.iter().enumerate() {
+        let standing = &mut standings[*bot];
+        standing.games += 1;
+        standing.total_score += scores[seat];
+        if result.timed_out[seat] {
+            standing.timeouts += 1;
+        }
+        for (other, other_score) in scores.iter().enumerate() {
+            if other != seat {
+                standing.points += match scores[seat].cmp(other_score) {
+                    std::cmp::Ordering::Greater => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Less => 0.0,
+                };
+            }
+        }
+    }
+}
+
+type Outcome = (Match, Result<GameResult, String>);
+
+/// Play matches on worker threads. Outcomes are in the same order as the
+/// matches.
+fn run_matches(
+    bots: &[Bot],
+    maps: &[Map],
+    matches: Vec<Match>,
+    params: &TournamentParameters,
+    first_seed: i64,
+) -> Vec<Outcome> {
+    let queue: VecDeque<(usize, Match)> = matches.into_iter().enumerate().collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..params.threads.max(1) {
+            let queue = Arc::clone(&queue);
+            let sender = sender.clone();
+            scope.spawn(move || loop {
+                let next = queue.lock().expect("Match queue").pop_front();
+                let (index, game) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                let game_params = GameParameters {
+                    player_seed: first_seed + index as i64,
+                    ..params.game.clone()
+                };
+                let outcome = play_match(bots, &maps[game.map], &game, &game_params);
+                if sender.send((index, (game, outcome))).is_err() {
+                    break;
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut outcomes: Vec<(usize, Outcome)> = receiver.into_iter().collect();
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+fn play_match(
+    bots: &[Bot],
+    map: &Map,
+    game: &Match,
+    params: &GameParameters,
+) -> Result<GameResult, String> {
+    let mut agents = game
+        .seats
+        .iter()
+        .map(|bot| bots[*bot].create_agent())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut agent_refs: Vec<&mut dyn Agent> = agents
+        .iter_mut()
+        .map(|a| a.as_mut() as &mut dyn Agent)
+        .collect();
+    Ok(play_game(map, params, &mut agent_refs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapgen::{generate, MapGenParameters};
+    use super::super::{Orders, WorldState};
+    use super::*;
+
+    /// Walks all ants in one direction.
+    struct Walker(super::super::Direction);
+
+    impl Agent for Walker {
+        fn prepare(&mut self, _params: GameParameters) {}
+
+        fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+            world
+                .live_ants_for_player(0)
+                .iter()
+                .map(|ant| ant.order(self.0))
+                .collect()
+        }
+    }
+
+    /// Never moves.
+    struct Idler;
+
+    impl Agent for Idler {
+        fn prepare(&mut self, _params: GameParameters) {}
+
+        fn make_turn(&mut self, _world: &WorldState, _turn_count: u32) -> Orders {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn combinations_success() {
+        assert_eq!(vec![vec![0, 1, 2]], combinations(3, 3));
+        assert_eq!(4, combinations(4, 3).len());
+        assert_eq!(Vec::<Vec<usize>>::new(), combinations(2, 3));
+    }
+
+    #[test]
+    fn swiss_round_groups_by_ranking() {
+        let matches = swiss_round(&[4, 2, 0, 1, 3], 1, &[2, 2], &[]);
+        assert_eq!(
+            vec![
+                Match {
+                    map: 1,
+                    seats: vec![4, 2]
+                },
+                Match {
+                    map: 1,
+                    seats: vec![0, 1]
+                }
+            ],
+            matches
+        );
+    }
+
+    #[test]
+    fn swiss_round_rotates_byes_and_avoids_rematches() {
+        let mut played = vec![];
+        let mut byes = vec![];
+        for round in 0..5 {
+            let matches = swiss_round(&[0, 1, 2, 3, 4], round, &[2], &played);
+            assert_eq!(2, matches.len());
+            let seated: Vec<usize> = matches.iter().flat_map(|m| m.seats.clone()).collect();
+            byes.extend((0..5).filter(|bot| !seated.contains(bot)));
+            played.extend(matches);
+        }
+
+        byes.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3, 4], byes);
+        let pairs: HashSet<Vec<usize>> = played.iter().map(|m| sorted(&m.seats)).collect();
+        assert_eq!(played.len(), pairs.len());
+    }
+
+    fn tournament(pairing: Pairing) -> TournamentResult {
+        let bots = vec![
+            Bot::in_process("idler", || Box::new(Idler)),
+            Bot::in_process("walker", || {
+                Box::new(Walker(super::super::Direction::North))
+            }),
+            Bot::command("missing", "/no/such/bot"),
+        ];
+        let map = generate(&MapGenParameters {
+            rows: 24,
+            cols: 24,
+            ..MapGenParameters::default()
+        })
+        .unwrap();
+        let params = TournamentParameters {
+            game: GameParameters {
+                turns: 30,
+                ..GameParameters::official()
+            },
+            pairing,
+            threads: 2,
+        };
+        run_tournament(&bots, &[map], &params)
+    }
+
+    #[test]
+    fn round_robin_tournament() {
+        let result = tournament(Pairing::RoundRobin);
+
+        assert_eq!(1, result.games.len());
+        assert_eq!(2, result.errors.len());
+        assert_eq!(3, result.standings.len());
+        let played: u32 = result.standings.iter().map(|s| s.games).sum();
+        assert_eq!(2, played);
+        assert_eq!(1.0, result.standings.iter().map(|s| s.points).sum::<f64>());
+    }
+
+    #[test]
+    fn swiss_tournament_is_deterministic() {
+        let result = tournament(Pairing::Swiss { rounds: 3 });
+
+        assert_eq!(3, result.games.len() + result.errors.len());
+        assert_eq!(tournament(Pairing::Swiss { rounds: 3 }), result);
+    }
+}
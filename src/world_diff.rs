@@ -0,0 +1,353 @@
+//! Changes between the world states of two consecutive turns.
+//!
+//! Two world states alone are not enough to tell what changed: water is
+//! only sent the first time it is seen and hills only while in sight, so
+//! the diff takes a `MapMemory` of the turns before for both. Telling
+//! things that came into or went out of sight from real changes needs the
+//! view radius, and following ants around the edges of the map needs its
+//! size, both from the `GameParameters`.
+
+use super::map_memory::MapMemory;
+use super::{GameParameters, Order, Position, WorldState};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What happened to one of our ants from one turn to the next.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub enum OwnAnt {
+    /// Alive at the new position, which is the old one if the ant had no
+    /// order, was held or was blocked by water.
+    Moved { from: Position, to: Position },
+    /// Died at the position it moved to, e.g. in a collision or battle.
+    Died { from: Position, at: Position },
+    /// Neither alive nor dead where it was expected to be.
+    Missing { from: Position },
+}
+
+/// Changes between the world states of two consecutive turns, as seen by
+/// player 0.
+///
+/// Things that merely came into or went out of sight are told apart from
+/// real changes where the world states allow it, using the view radius
+/// around our ants in each turn.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct WorldDiff {
+    /// Live ants now seen where there were none before, per player.
+    pub ants_appeared: Vec<Vec<Position>>,
+    /// Live ants no longer seen where they were, per player.
+    pub ants_disappeared: Vec<Vec<Position>>,
+    /// Dead ants of the new turn, per player.
+    pub dead_ants: Vec<Vec<Position>>,
+    /// Food on tiles that were visible without food.
+    pub food_spawned: Vec<Position>,
+    /// Food on tiles that were not visible.
+    pub food_discovered: Vec<Position>,
+    /// Food gone from tiles that are still visible.
+    pub food_eaten: Vec<Position>,
+    /// Water neither in the memory nor in the previous turn.
+    pub new_water: Vec<Position>,
+    /// Hills not known before, per player.
+    pub hills_discovered: Vec<Vec<Position>>,
+    /// Hills gone from tiles that are still visible, per player.
+    pub hills_razed: Vec<Vec<Position>>,
+    /// Fate of each of our previous ants.
+    pub own_ants: Vec<OwnAnt>,
+    /// Our ants that are not accounted for by moves, i.e. newly spawned.
+    pub own_spawned: Vec<Position>,
+}
+
+impl WorldDiff {
+    /// Diff from `prev` to `next`, given the orders we gave in the turn of
+    /// `prev`. Orders may be empty if unknown, in which case our ants are
+    /// expected to stay.
+    ///
+    /// The memory should hold `prev` and the turns before, but not `next`.
+    pub fn between(
+        prev: &WorldState,
+        next: &WorldState,
+        orders: &[Order],
+        memory: &MapMemory,
+        params: &GameParameters,
+    ) -> WorldDiff {
+        let size = Position {
+            row: params.rows as u16,
+            col: params.cols as u16,
+        };
+        let players = std::cmp::max(prev.max_player_count(), next.max_player_count());
+        let per_player = |f: &dyn Fn(u8) -> Vec<Position>| -> Vec<Vec<Position>> {
+            (0..players as u8).map(f).collect()
+        };
+        let visible = |world: &WorldState, p: &Position| {
+            world
+                .live_ants
+                .first()
+                .map(|ants| {
+                    ants.iter()
+                        .any(|a| a.distance2(p, &size) <= params.viewradius2)
+                })
+                .unwrap_or(false)
+        };
+
+        let (own_ants, own_spawned) = own_ants(prev, next, orders, memory, &size);
+
+        WorldDiff {
+            ants_appeared: per_player(&|player| {
+                minus(
+                    &next.live_ants_for_player(player),
+                    &prev.live_ants_for_player(player),
+                )
+            }),
+            ants_disappeared: per_player(&|player| {
+                minus(
+                    &prev.live_ants_for_player(player),
+                    &next.live_ants_for_player(player),
+                )
+            }),
+            dead_ants: per_player(&|player| next.dead_ants_for_player(player)),
+            food_spawned: minus(&next.foods, &prev.foods)
+                .into_iter()
+                .filter(|f| visible(prev, f))
+                .collect(),
+            food_discovered: minus(&next.foods, &prev.foods)
+                .into_iter()
+                .filter(|f| !visible(prev, f))
+                .collect(),
+            food_eaten: minus(&prev.foods, &next.foods)
+                .into_iter()
+                .filter(|f| visible(next, f))
+                .collect(),
+            new_water: minus(&next.waters, &prev.waters)
+                .into_iter()
+                .filter(|w| !memory.is_water(w))
+                .collect(),
+            hills_discovered: per_player(&|player| {
+                minus(
+                    &next.hills_for_player(player),
+                    &prev.hills_for_player(player),
+                )
+                .into_iter()
+                .filter(|h| memory.hill(h) != Some(player))
+                .collect()
+            }),
+            hills_razed: per_player(&|player| {
+                minus(
+                    &prev.hills_for_player(player),
+                    &next.hills_for_player(player),
+                )
+                .into_iter()
+                .filter(|h| visible(next, h))
+                .collect()
+            }),
+            own_ants,
+            own_spawned,
+        }
+    }
+}
+
+/// Positions in `a` but not in `b`, in the order of `a`.
+fn minus(a: &[Position], b: &[Position]) -> Vec<Position> {
+    let b: BTreeSet<&Position> = b.iter().collect();
+    a.iter().filter(|p| !b.contains(p)).cloned().collect()
+}
+
+/// Follow our ants by their orders, as the server moves them: orders into
+/// water are ignored, and ants moving onto the same tile all die.
+fn own_ants(
+    prev: &WorldState,
+    next: &WorldState,
+    orders: &[Order],
+    memory: &MapMemory,
+    size: &Position,
+) -> (Vec<OwnAnt>, Vec<Position>) {
+    let sent: BTreeSet<&Position> = prev.waters.iter().chain(next.waters.iter()).collect();
+    let is_water = |p: &Position| sent.contains(p) || memory.is_water(p);
+    let ordered: BTreeMap<&Position, &Order> = orders.iter().map(|o| (&o.pos, o)).collect();
+    let alive: BTreeSet<Position> = next.live_ants_for_player(0).into_iter().collect();
+    let dead: BTreeSet<Position> = next.dead_ants_for_player(0).into_iter().collect();
+
+    let mut fates = vec![];
+    let mut accounted = BTreeSet::new();
+    for from in prev.live_ants_for_player(0) {
+        let target = ordered
+            .get(&from)
+            .map(|o| o.target_pos(size))
+            .filter(|t| !is_water(t))
+            .unwrap_or_else(|| from.clone());
+        fates.push(if alive.contains(&target) {
+            accounted.insert(target.clone());
+            OwnAnt::Moved { from, to: target }
+        } else if dead.contains(&target) {
+            OwnAnt::Died { from, at: target }
+        } else {
+            OwnAnt::Missing { from }
+        });
+    }
+
+    let spawned = next
+        .live_ants_for_player(0)
+        .into_iter()
+        .filter(|a| !accounted.contains(a))
+        .collect();
+    (fates, spawned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pos;
+    use super::super::scenario::world;
+    use super::*;
+
+    fn params(viewradius2: i64) -> GameParameters {
+        GameParameters {
+            rows: 3,
+            cols: 8,
+            viewradius2,
+            ..GameParameters::default()
+        }
+    }
+
+    fn nothing_known() -> MapMemory {
+        MapMemory::new(&params(0))
+    }
+
+    #[test]
+    fn own_ants_follow_orders() {
+        let prev = world("aa.%a..b\n0.......\n....a...");
+        let next = world("a.a%a...\nA......b\n........").dead_ant(pos(2, 5), 0);
+        let orders = [
+            pos(0, 1).east(),
+            pos(0, 4).west(),
+            pos(2, 4).east(),
+            pos(0, 0).pause(),
+        ];
+
+        let diff = WorldDiff::between(&prev, &next, &orders, &nothing_known(), &params(4));
+
+        assert_eq!(
+            vec![
+                OwnAnt::Moved {
+                    from: pos(0, 0),
+                    to: pos(0, 0)
+                },
+                OwnAnt::Moved {
+                    from: pos(0, 1),
+                    to: pos(0, 2)
+                },
+                OwnAnt::Moved {
+                    from: pos(0, 4),
+                    to: pos(0, 4)
+                },
+                OwnAnt::Died {
+                    from: pos(2, 4),
+                    at: pos(2, 5)
+                },
+            ],
+            diff.own_ants
+        );
+        assert_eq!(vec![pos(1, 0)], diff.own_spawned);
+        assert_eq!(
+            vec![vec![pos(0, 2), pos(1, 0)], vec![pos(1, 7)]],
+            diff.ants_appeared
+        );
+        assert_eq!(
+            vec![vec![pos(0, 1), pos(2, 4)], vec![pos(0, 7)]],
+            diff.ants_disappeared
+        );
+        assert_eq!(vec![vec![pos(2, 5)], vec![]], diff.dead_ants);
+    }
+
+    #[test]
+    fn missing_ant_without_trace() {
+        let diff = WorldDiff::between(
+            &world("a.\n.."),
+            &world("..\n.."),
+            &[],
+            &nothing_known(),
+            &params(4),
+        );
+
+        assert_eq!(vec![OwnAnt::Missing { from: pos(0, 0) }], diff.own_ants);
+    }
+
+    #[test]
+    fn food_and_hills_out_of_sight_are_not_changes() {
+        // Our ant wraps around onto the enemy hill, losing sight of the food
+        let prev = world("a*..*..1\n...%....\n........");
+        let next = world(".......a\n...%%...\n....*...");
+
+        let diff = WorldDiff::between(
+            &prev,
+            &next,
+            &[pos(0, 0).west()],
+            &nothing_known(),
+            &params(2),
+        );
+
+        assert_eq!(
+            vec![OwnAnt::Moved {
+                from: pos(0, 0),
+                to: pos(0, 7)
+            }],
+            diff.own_ants
+        );
+        assert_eq!(vec![vec![], vec![pos(0, 7)]], diff.hills_razed);
+        assert_eq!(vec![pos(2, 4)], diff.food_discovered);
+        assert!(diff.food_spawned.is_empty());
+        assert!(diff.food_eaten.is_empty());
+        assert_eq!(vec![pos(1, 4)], diff.new_water);
+
+        let later = world("......*a\n...%%...\n........");
+        let diff = WorldDiff::between(&next, &later, &[], &nothing_known(), &params(2));
+        assert_eq!(vec![pos(0, 6)], diff.food_spawned);
+        assert!(diff.food_eaten.is_empty());
+
+        let diff = WorldDiff::between(
+            &later,
+            &world(".......a\n........\n..0....."),
+            &[],
+            &nothing_known(),
+            &params(2),
+        );
+        assert_eq!(vec![pos(0, 6)], diff.food_eaten);
+        assert_eq!(vec![vec![pos(2, 2)]], diff.hills_discovered);
+        assert!(diff.new_water.is_empty());
+    }
+
+    #[test]
+    fn water_seen_in_earlier_turns() {
+        // Water is sent in the first turn only
+        let mut memory = MapMemory::new(&params(4));
+        memory.update(&world("a%......\n........\n........"), 1);
+        let prev = world("a.......\n........\n........");
+        let next = world("a.%.....\n........\n........");
+
+        let diff = WorldDiff::between(&prev, &next, &[pos(0, 0).east()], &memory, &params(4));
+
+        assert_eq!(
+            vec![OwnAnt::Moved {
+                from: pos(0, 0),
+                to: pos(0, 0)
+            }],
+            diff.own_ants
+        );
+        assert!(diff.own_spawned.is_empty());
+        assert_eq!(vec![pos(0, 2)], diff.new_water);
+
+        let diff = WorldDiff::between(&prev, &prev, &[], &memory, &params(4));
+        assert!(diff.new_water.is_empty());
+    }
+
+    #[test]
+    fn hill_back_in_sight_is_not_discovered() {
+        let seen = world("a.1.....\n........\n........");
+        let gone = world(".......a\n........\n........");
+        let mut memory = MapMemory::new(&params(4));
+        memory.update(&seen, 1);
+        memory.update(&gone, 2);
+
+        let diff = WorldDiff::between(&gone, &seen, &[], &memory, &params(4));
+
+        assert_eq!(vec![Vec::<Position>::new(); 2], diff.hills_discovered);
+        let diff = WorldDiff::between(&gone, &seen, &[], &nothing_known(), &params(4));
+        assert_eq!(vec![vec![], vec![pos(0, 2)]], diff.hills_discovered);
+    }
+}
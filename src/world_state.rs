@@ -1,6 +1,6 @@
 use super::Position;
 
-#[derive(PartialEq, Eq, Debug, Default)]
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
 pub struct WorldState {
     /// Food positions
     pub foods: Vec<Position>,
@@ -48,6 +48,16 @@ impl WorldState {
         self
     }
 
+    /// Remove everything, keeping the allocated capacity so that the
+    /// state can be filled again for the next turn.
+    pub fn clear(&mut self) {
+        self.foods.clear();
+        self.waters.clear();
+        for per_player in [&mut self.live_ants, &mut self.dead_ants, &mut self.hills] {
+            per_player.iter_mut().for_each(Vec::clear);
+        }
+    }
+
     pub fn max_player_count(&self) -> usize {
         std::cmp::max(
             self.live_ants.len(),
@@ -66,6 +76,77 @@ impl WorldState {
     pub fn hills_for_player(&self, player: u8) -> Vec<Position> {
         get_or_empty(&self.hills, player)
     }
+
+    /// Our live ants. The bot is always player 0.
+    pub fn my_ants(&self) -> &[Position] {
+        self.live_ants.first().map_or(&[], |ants| ants.as_slice())
+    }
+
+    /// Live ants of all other players, with their owners.
+    pub fn enemy_ants(&self) -> Vec<(Position, u8)> {
+        with_owners(&self.live_ants)
+            .skip_while(|(_, o)| *o == 0)
+            .collect()
+    }
+
+    /// Our hills that have not been razed.
+    pub fn my_hills(&self) -> &[Position] {
+        self.hills.first().map_or(&[], |hills| hills.as_slice())
+    }
+
+    /// Hills of all other players, with their owners.
+    pub fn enemy_hills(&self) -> Vec<(Position, u8)> {
+        with_owners(&self.hills)
+            .skip_while(|(_, o)| *o == 0)
+            .collect()
+    }
+
+    /// Without the empty per player lists after the last player with any
+    /// positions, as left behind by `clear`, to compare with fresh worlds.
+    #[cfg(test)]
+    pub(crate) fn trimmed(mut self) -> Self {
+        for per_player in [&mut self.live_ants, &mut self.dead_ants, &mut self.hills] {
+            while per_player
+                .last()
+                .is_some_and(|positions| positions.is_empty())
+            {
+                per_player.pop();
+            }
+        }
+        self
+    }
+
+    /// The world as the given player sees it, in a game of the given
+    /// number of players. Owners are renumbered as by the server: the
+    /// player becomes 0 and the players after it follow in turn. This
+    /// lets bot code run on full information worlds, e.g. of replays.
+    pub fn from_perspective(&self, player: u8, players: u8) -> WorldState {
+        let players = players.max(1) as usize;
+        let renumber =
+            |owner: u8| ((owner as usize + players - player as usize % players) % players) as u8;
+        let mut world = WorldState {
+            foods: self.foods.clone(),
+            waters: self.waters.clone(),
+            ..WorldState::default()
+        };
+        for (p, owner) in with_owners(&self.live_ants) {
+            world = world.live_ant(p, renumber(owner));
+        }
+        for (p, owner) in with_owners(&self.dead_ants) {
+            world = world.dead_ant(p, renumber(owner));
+        }
+        for (p, owner) in with_owners(&self.hills) {
+            world = world.hill(p, renumber(owner));
+        }
+        world
+    }
+}
+
+fn with_owners(per_player: &[Vec<Position>]) -> impl Iterator<Item = (Position, u8)> + '_ {
+    per_player
+        .iter()
+        .enumerate()
+        .flat_map(|(owner, positions)| positions.iter().map(move |p| (p.clone(), owner as u8)))
 }
 
 fn ensure_capacity(vec: &mut Vec<Vec<Position>>, capacity: u8) {
@@ -137,4 +218,69 @@ mod tests {
         assert_eq!(actual.hills_for_player(2), vec![]);
         assert_eq!(actual.hills_for_player(3), vec![]);
     }
+
+    #[test]
+    fn perspective_accessors() {
+        let world = WorldState::default()
+            .live_ant(pos(1, 1), 0)
+            .live_ant(pos(2, 2), 2)
+            .live_ant(pos(3, 3), 1)
+            .hill(pos(4, 4), 0)
+            .hill(pos(5, 5), 2);
+
+        assert_eq!(&[pos(1, 1)], world.my_ants());
+        assert_eq!(vec![(pos(3, 3), 1), (pos(2, 2), 2)], world.enemy_ants());
+        assert_eq!(&[pos(4, 4)], world.my_hills());
+        assert_eq!(vec![(pos(5, 5), 2)], world.enemy_hills());
+        assert!(WorldState::default().my_ants().is_empty());
+        assert!(WorldState::default().enemy_hills().is_empty());
+    }
+
+    #[test]
+    fn from_perspective_renumbers_owners() {
+        let world = WorldState::default()
+            .food(pos(0, 0))
+            .water(pos(0, 1))
+            .live_ant(pos(1, 1), 0)
+            .live_ant(pos(2, 2), 1)
+            .live_ant(pos(3, 3), 2)
+            .dead_ant(pos(4, 4), 2)
+            .hill(pos(5, 5), 1);
+
+        let expected = WorldState::default()
+            .food(pos(0, 0))
+            .water(pos(0, 1))
+            .live_ant(pos(2, 2), 0)
+            .live_ant(pos(3, 3), 1)
+            .live_ant(pos(1, 1), 2)
+            .dead_ant(pos(4, 4), 1)
+            .hill(pos(5, 5), 0);
+        assert_eq!(expected, world.from_perspective(1, 3));
+        assert_eq!(world, world.from_perspective(0, 3));
+        assert_eq!(&[pos(3, 3)], world.from_perspective(2, 3).my_ants());
+    }
+
+    #[test]
+    fn clear_keeps_capacity() {
+        let mut world = WorldState::default()
+            .food(pos(1, 1))
+            .live_ant(pos(2, 2), 0)
+            .dead_ant(pos(3, 3), 2);
+        let capacity = world.live_ants[0].capacity();
+
+        world.clear();
+
+        assert_eq!(WorldState::default(), world.clone().trimmed());
+        assert_eq!(3, world.max_player_count());
+        assert_eq!(capacity, world.live_ants[0].capacity());
+        world = world.live_ant(pos(4, 4), 1);
+        assert_eq!(
+            WorldState::default().live_ant(pos(4, 4), 1),
+            world.clone().trimmed()
+        );
+        assert_ne!(
+            WorldState::default().live_ant(pos(4, 4), 0),
+            world.trimmed()
+        );
+    }
 }
@@ -0,0 +1,80 @@
+#![cfg(unix)]
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::process_bot::BotStatus;
+use ants_ai_challenge_api::*;
+use std::process::Command;
+
+/// Shell bot answering every turn with the given order lines.
+fn shell_bot(turn_answer: &str) -> ProcessBot {
+    let script = format!(
+        "while read line; do case \"$line\" in ready) echo go;; go) {} echo go;; esac; done",
+        turn_answer
+    );
+    ProcessBot::spawn_command(Command::new("sh").arg("-c").arg(script), None).unwrap()
+}
+
+fn params() -> GameParameters {
+    GameParameters {
+        loadtime_ms: 2000,
+        turntime_ms: 300,
+        rows: 20,
+        cols: 20,
+        ..GameParameters::default()
+    }
+}
+
+#[test]
+fn process_bot_makes_turns() {
+    let mut bot = shell_bot("echo 'o 1 2 N'; echo 'bad line'; echo 'o 3 4 E';");
+
+    bot.prepare(params());
+    let orders = bot.make_turn(&WorldState::default().live_ant(pos(1, 2), 0), 1);
+
+    assert_eq!(vec![pos(1, 2).north(), pos(3, 4).east()], orders);
+    assert_eq!(BotStatus::Alive, bot.status());
+}
+
+#[test]
+fn process_bot_times_out() {
+    let mut bot = shell_bot("sleep 2;");
+
+    bot.prepare(params());
+    assert_eq!(BotStatus::Alive, bot.status());
+
+    let orders = bot.make_turn(&WorldState::default(), 1);
+    assert_eq!(Orders::new(), orders);
+    assert_eq!(BotStatus::TimedOut, bot.status());
+
+    let orders = bot.make_turn(&WorldState::default(), 2);
+    assert_eq!(Orders::new(), orders);
+    assert_eq!(BotStatus::TimedOut, bot.status());
+}
+
+#[test]
+fn process_bot_without_time_limits_waits() {
+    let mut bot = shell_bot("sleep 0.5; echo 'o 1 2 N';").without_time_limits();
+
+    bot.prepare(params());
+    let orders = bot.make_turn(&WorldState::default(), 1);
+
+    assert_eq!(vec![pos(1, 2).north()], orders);
+    assert_eq!(BotStatus::Alive, bot.status());
+}
+
+#[test]
+fn process_bot_crashes() {
+    let mut bot = shell_bot("exit 1;");
+
+    bot.prepare(params());
+    let orders = bot.make_turn(&WorldState::default(), 1);
+
+    assert_eq!(Orders::new(), orders);
+    assert_eq!(BotStatus::Crashed, bot.status());
+}
+
+#[test]
+fn process_bot_fails_to_start() {
+    assert!(ProcessBot::spawn("", None).is_err());
+    assert!(ProcessBot::spawn("/no/such/bot", None).is_err());
+}
@@ -11,7 +11,6 @@ struct TestAgent {
     orders_to_make: Orders,
     expected_game_params: GameParameters,
     expected_world_state: WorldState,
-    expected_score: Score,
 }
 
 impl Agent for TestAgent {
@@ -23,18 +22,23 @@ impl Agent for TestAgent {
         );
     }
 
-    fn make_turn(&mut self, world: WorldState, turn_count: u32) -> Orders {
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders {
         self.make_turn_call_count += 1;
         assert_eq!(
             self.make_turn_call_count, turn_count,
             "Turn count, expecting left but go right"
         );
         assert_eq!(
-            self.expected_world_state, world,
+            self.expected_world_state, *world,
             "Expecting left but got right"
         );
         self.orders_to_make.clone()
     }
+
+    fn at_end(&mut self, world: WorldState, _score: Score) {
+        self.at_end_call_count += 1;
+        assert_eq!(self.expected_world_state, world, "World state at end");
+    }
 }
 
 #[test]
@@ -135,6 +139,7 @@ fn run_game_success() {
         per_player: vec![1, 0],
     };
     assert_eq!(expected_score, score, "Score at game end");
+    assert_eq!(1, test_agent.at_end_call_count, "one at_end call after use");
 
     assert_eq!(
         indoc!(
@@ -149,3 +154,72 @@ fn run_game_success() {
         "Output of agent, expecting left but got right"
     );
 }
+
+struct DebuggingAgent;
+
+impl Agent for DebuggingAgent {
+    fn prepare(&mut self, _params: GameParameters) {}
+
+    fn make_turn(&mut self, world: &WorldState, turn_count: u32) -> Orders {
+        self.make_turn_with_debug(world, turn_count, &mut DebugSink::disabled())
+    }
+
+    fn make_turn_with_debug(
+        &mut self,
+        world: &WorldState,
+        _turn_count: u32,
+        debug: &mut DebugSink,
+    ) -> Orders {
+        let ant = world.live_ants_for_player(0)[0].clone();
+        debug.label(ant.clone(), "going north");
+        vec![ant.north()]
+    }
+}
+
+#[test]
+fn run_game_recorded_success() {
+    let input = indoc!(
+        "turn 0
+        rows 20
+        cols 20
+        ready
+
+        turn 1
+        f 6 5
+        a 10 8 0
+        go
+
+        end
+        players 1
+        score 1
+        a 9 8 0
+        go
+        "
+    );
+    let mut output = String::from("");
+    let mut add_outputln = |line: String| output.push_str(&line);
+
+    let (_, _, replay) = run_game_recorded_with_io(
+        &mut DebuggingAgent,
+        input.lines().map(String::from),
+        &mut add_outputln,
+    );
+
+    assert_eq!(20, replay.params.rows);
+    assert_eq!(1, replay.turns.len());
+    assert_eq!(
+        WorldState::default()
+            .food(pos(6, 5))
+            .live_ant(pos(10, 8), 0),
+        replay.turns[0].world
+    );
+    assert_eq!(vec![pos(10, 8).north()], replay.turns[0].orders);
+    assert_eq!(
+        vec![debug::Annotation::Label {
+            pos: pos(10, 8),
+            text: String::from("going north")
+        }],
+        replay.turns[0].debug
+    );
+    assert_eq!("go\no 10 8 N\ngo\n", output);
+}
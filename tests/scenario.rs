@@ -0,0 +1,60 @@
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::scenario::Scenario;
+use ants_ai_challenge_api::*;
+
+/// Steps onto food next to its ants, if any.
+struct Forager {
+    size: Position,
+}
+
+impl Agent for Forager {
+    fn prepare(&mut self, params: GameParameters) {
+        self.size = pos(params.rows as u16, params.cols as u16);
+    }
+
+    fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+        let size = &self.size;
+        world
+            .live_ants_for_player(0)
+            .iter()
+            .filter_map(|ant| {
+                Direction::ALL
+                    .iter()
+                    .map(|dir| ant.order(*dir))
+                    .find(|order| world.foods.contains(&order.target_pos(size)))
+            })
+            .collect()
+    }
+}
+
+fn forager() -> Forager {
+    Forager { size: pos(0, 0) }
+}
+
+#[test]
+fn steps_onto_adjacent_food() {
+    assert_orders!(forager(), "..a*.\n%%0..\n", contains: "o 0 2 E");
+    assert_orders!(
+        forager(),
+        "
+        *...
+        a..b
+        .a*.
+        ",
+        equals: "o 1 0 N", "o 2 1 E"
+    );
+}
+
+#[test]
+fn finds_food_across_map_edge() {
+    assert_orders!(forager(), "a..*\n....\n", equals: "o 0 0 W");
+    assert_orders!(forager(), "a...\n....\n", equals:);
+}
+
+#[test]
+fn ignores_enemy_ants_and_water() {
+    let scenario = Scenario::parse("b*%\n.a.\n").unwrap().turn(12);
+    assert_orders!(forager(), &scenario, equals: "o 1 1 N");
+    assert_orders!(forager(), scenario, excludes: "o 0 0 E");
+}
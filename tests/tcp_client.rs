@@ -0,0 +1,115 @@
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::tcp_client::run_game_tcp;
+use ants_ai_challenge_api::tcp_server::{GameServer, ServerParameters};
+use ants_ai_challenge_api::*;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+
+/// Walks all ants east.
+struct Walker {
+    prepared: bool,
+}
+
+impl Agent for Walker {
+    fn prepare(&mut self, _params: GameParameters) {
+        self.prepared = true;
+    }
+
+    fn make_turn(&mut self, world: &WorldState, _turn_count: u32) -> Orders {
+        world
+            .live_ants_for_player(0)
+            .iter()
+            .map(|a| a.order(East))
+            .collect()
+    }
+}
+
+fn start_server() -> SocketAddr {
+    let map =
+        Map::parse("rows 3\ncols 8\nplayers 2\nm .A...B..\nm ........\nm ........\n").unwrap();
+    let mut params = ServerParameters::new(vec![map]);
+    params.game = GameParameters {
+        turns: 4,
+        loadtime_ms: 2000,
+        turntime_ms: 2000,
+        ..GameParameters::official()
+    };
+    let server = GameServer::new(params).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || server.serve_players(listener));
+    addr
+}
+
+#[test]
+fn run_game_tcp_plays_consecutive_games() {
+    let addr = start_server();
+
+    let other = thread::spawn(move || {
+        run_game_tcp(&mut Walker { prepared: false }, addr, "other", "pw", 2).unwrap()
+    });
+    let mut agent = Walker { prepared: false };
+    let results = run_game_tcp(&mut agent, addr, "me", "pw", 2).unwrap();
+    let other_results = other.join().unwrap();
+
+    assert!(agent.prepared);
+    assert_eq!(2, results.len());
+    assert_eq!(2, other_results.len());
+    for (mine, theirs) in results.iter().zip(other_results.iter()) {
+        assert!(mine.turns > 0);
+        assert_eq!(theirs.turns, mine.turns);
+        assert_eq!(mine.turns as usize, mine.replay.turns.len());
+        assert_eq!(mine.score.per_player[0], theirs.score.per_player[1]);
+    }
+}
+
+#[test]
+fn run_game_tcp_fails_on_wrong_password() {
+    let addr = start_server();
+
+    let first = thread::spawn(move || {
+        run_game_tcp(&mut Walker { prepared: false }, addr, "me", "pw", 1).unwrap()
+    });
+    thread::sleep(std::time::Duration::from_millis(100));
+    let error = run_game_tcp(&mut Walker { prepared: false }, addr, "me", "guess", 1).unwrap_err();
+    assert!(error.to_string().contains("wrong password"));
+
+    run_game_tcp(&mut Walker { prepared: false }, addr, "you", "pw", 1).unwrap();
+    first.join().unwrap();
+}
+
+#[test]
+fn run_game_tcp_reconnects_on_drop() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        // Drop the first connection during the game, play the second
+        for (i, stream) in listener.incoming().take(2).enumerate() {
+            let mut stream = stream.unwrap();
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+            assert_eq!("USER me pw", lines.next().unwrap().unwrap());
+            stream
+                .write_all(b"INFO: welcome\nturn 0\nrows 4\ncols 4\nready\n")
+                .unwrap();
+            assert_eq!("go", lines.next().unwrap().unwrap());
+            if i == 0 {
+                continue;
+            }
+            stream.write_all(b"turn 1\na 1 1 0\ngo\n").unwrap();
+            assert_eq!("o 1 1 E", lines.next().unwrap().unwrap());
+            assert_eq!("go", lines.next().unwrap().unwrap());
+            stream
+                .write_all(b"end\nplayers 2\nscore 1 0\na 1 2 0\ngo\n")
+                .unwrap();
+        }
+    });
+
+    let results = run_game_tcp(&mut Walker { prepared: false }, addr, "me", "pw", 1).unwrap();
+    server.join().unwrap();
+
+    assert_eq!(1, results.len());
+    assert_eq!(vec![1, 0], results[0].score.per_player);
+    assert_eq!(1, results[0].turns);
+}
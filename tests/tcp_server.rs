@@ -0,0 +1,202 @@
+extern crate ants_ai_challenge_api;
+
+use ants_ai_challenge_api::tcp_server::{GameServer, ServerParameters};
+use ants_ai_challenge_api::{GameParameters, Map};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn start_server() -> (GameServer, SocketAddr, SocketAddr) {
+    start_server_with(|_| {})
+}
+
+fn start_server_with(
+    configure: impl FnOnce(&mut ServerParameters),
+) -> (GameServer, SocketAddr, SocketAddr) {
+    let map =
+        Map::parse("rows 3\ncols 8\nplayers 2\nm .A...B..\nm ........\nm ........\n").unwrap();
+    let mut params = ServerParameters::new(vec![map]);
+    params.game = GameParameters {
+        turns: 3,
+        loadtime_ms: 2000,
+        turntime_ms: 2000,
+        ..GameParameters::official()
+    };
+    configure(&mut params);
+    let server = GameServer::new(params).unwrap();
+
+    let players = TcpListener::bind("127.0.0.1:0").unwrap();
+    let status = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addresses = (players.local_addr().unwrap(), status.local_addr().unwrap());
+    let player_server = server.clone();
+    thread::spawn(move || player_server.serve_players(players));
+    let status_server = server.clone();
+    thread::spawn(move || status_server.serve_status(status));
+    (server, addresses.0, addresses.1)
+}
+
+/// Log in and play one game without giving orders, returning all lines
+/// received from the server.
+fn play(addr: SocketAddr, user: &str, password: &str) -> Vec<String> {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(format!("USER {} {}\n", user, password).as_bytes())
+        .unwrap();
+    let mut lines = vec![];
+    let mut ended = false;
+    for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        match line.as_str() {
+            "end" => ended = true,
+            "ready" | "go" if !ended => stream.write_all(b"go\n").unwrap(),
+            _ => {}
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn wait_for_games(server: &GameServer, count: usize) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while server.games().len() < count {
+        assert!(Instant::now() < deadline, "Games did not finish");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn plays_game_between_logged_in_bots() {
+    let (server, addr, status) = start_server();
+
+    let alice = thread::spawn(move || play(addr, "alice", "secret"));
+    let bob = thread::spawn(move || play(addr, "bob", "hunter2"));
+    let alice = alice.join().unwrap();
+    let bob = bob.join().unwrap();
+    wait_for_games(&server, 1);
+
+    for lines in [&alice, &bob].iter() {
+        assert!(lines[0].starts_with("INFO:"));
+        assert_eq!("turn 0", lines[1]);
+        assert!(lines.contains(&"ready".to_string()));
+        assert!(lines.contains(&"end".to_string()));
+    }
+    let mut players = server.games()[0].players.clone();
+    players.sort();
+    assert_eq!(vec!["alice".to_string(), "bob".to_string()], players);
+    assert_eq!(3, server.games()[0].turns);
+    assert_eq!(1, server.ratings().get("bob").games);
+
+    let mut page = String::new();
+    let mut http = TcpStream::connect(status).unwrap();
+    http.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+    http.read_to_string(&mut page).unwrap();
+    assert!(page.starts_with("HTTP/1.0 200 OK"));
+    assert!(page.contains("alice") && page.contains("bob"));
+}
+
+#[test]
+fn rejects_wrong_password_and_bad_login() {
+    let (server, addr, _) = start_server();
+
+    let first = thread::spawn(move || play(addr, "alice", "secret"));
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(
+        vec!["ERROR: wrong password".to_string()],
+        play(addr, "alice", "guess")
+    );
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"HELLO\n").unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("ERROR:"));
+
+    // Same bot twice does not make a game
+    let second = thread::spawn(move || play(addr, "alice", "secret"));
+    thread::sleep(Duration::from_millis(100));
+    assert!(server.games().is_empty());
+
+    play(addr, "bob", "pw");
+    first.join().unwrap();
+    wait_for_games(&server, 1);
+    play(addr, "carol", "pw");
+    second.join().unwrap();
+    wait_for_games(&server, 2);
+}
+
+#[test]
+fn disconnected_bots_are_not_seated() {
+    let (server, addr, status) = start_server();
+
+    let mut quitter = TcpStream::connect(addr).unwrap();
+    quitter.write_all(b"USER carol pw\n").unwrap();
+    let mut greeting = String::new();
+    BufReader::new(quitter.try_clone().unwrap())
+        .read_line(&mut greeting)
+        .unwrap();
+    assert!(greeting.starts_with("INFO:"));
+    drop(quitter);
+    thread::sleep(Duration::from_millis(100));
+
+    let mut page = String::new();
+    let mut http = TcpStream::connect(status).unwrap();
+    http.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+    http.read_to_string(&mut page).unwrap();
+    assert!(!page.contains("carol"));
+
+    let alice = thread::spawn(move || play(addr, "alice", "secret"));
+    play(addr, "bob", "pw");
+    alice.join().unwrap();
+    wait_for_games(&server, 1);
+
+    let mut players = server.games()[0].players.clone();
+    players.sort();
+    assert_eq!(vec!["alice".to_string(), "bob".to_string()], players);
+    assert_eq!(3, server.games()[0].turns);
+}
+
+#[test]
+fn games_get_their_own_seed() {
+    let (server, addr, _) = start_server();
+
+    let mut seeds = vec![];
+    for game in 1..=2 {
+        let alice = thread::spawn(move || play(addr, "alice", "secret"));
+        play(addr, "bob", "pw");
+        let lines = alice.join().unwrap();
+        wait_for_games(&server, game);
+        seeds.extend(lines.into_iter().filter(|l| l.starts_with("player_seed")));
+    }
+
+    assert_eq!(2, seeds.len());
+    assert_ne!(seeds[0], seeds[1]);
+}
+
+#[test]
+fn write_failures_are_recorded() {
+    let missing = std::env::temp_dir().join("ants_no_such_dir").join("nested");
+    let (server, addr, status) = start_server_with(|params| {
+        params.replay_dir = Some(missing.clone());
+        params.ratings_file = Some(missing.join("ratings.txt"));
+    });
+
+    let alice = thread::spawn(move || play(addr, "alice", "secret"));
+    play(addr, "bob", "pw");
+    alice.join().unwrap();
+    wait_for_games(&server, 1);
+
+    assert!(!server.games()[0].has_replay);
+    assert_eq!(2, server.errors().len());
+    assert!(server.errors()[0].contains("0.html"));
+    assert!(server.errors()[1].contains("ratings.txt"));
+
+    let mut page = String::new();
+    let mut http = TcpStream::connect(status).unwrap();
+    http.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+    http.read_to_string(&mut page).unwrap();
+    assert!(page.contains("ratings.txt"));
+}